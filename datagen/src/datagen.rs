@@ -12,7 +12,7 @@ use engine::{
     create_thread_data,
     eval::evaluation::{evaluate, EvalScore, INF, MATE_THRESHOLD},
     search::history_table::History,
-    search::search::{write_stop_flag, Ply, SearchLimit, SearchResults, Searcher},
+    search::search::{write_stop_flag, Ply, SearchLimit, SearchOptions, SearchResults, Searcher},
     search::transposition_table::TranspositionTable,
     tablebase::probe::Syzygy,
 };
@@ -137,11 +137,12 @@ impl DataGenerator {
     }
 
     fn record_game(&mut self) {
-        let mut positions: Vec<Board> = vec![];
+        // each position with the search score from white's point of view
+        let mut positions: Vec<(Board, EvalScore)> = vec![];
         let mut result = Self::DRAW;
 
         let mut history = History::new();
-        let tt = TranspositionTable::new(16);
+        let tt = TranspositionTable::new(16, 1);
         loop {
             create_thread_data!(thread_data);
             write_stop_flag(false);
@@ -153,6 +154,7 @@ impl DataGenerator {
                 &tt,
                 Syzygy::new(),
                 thread_data,
+                SearchOptions::default(),
             );
 
             let SearchResults { best_move, score } = searcher.go::<true>(&self.board, false);
@@ -177,7 +179,11 @@ impl DataGenerator {
                 break;
             }
 
-            positions.push(self.board.clone());
+            let white_score = match self.board.color_to_move {
+                Color::White => score,
+                Color::Black => -score,
+            };
+            positions.push((self.board.clone(), white_score));
             self.board.try_play_move(
                 best_move,
                 &mut self.zobrist_stack,
@@ -189,9 +195,9 @@ impl DataGenerator {
             }
         }
 
-        for board in positions {
+        for (board, score) in positions {
             if pos_is_quiet(&board) {
-                writeln!(&mut self.file, "{} [{}]", board.to_fen(), result).unwrap();
+                writeln!(&mut self.file, "{} [{}] {}", board.to_fen(), result, score).unwrap();
                 self.positions_written += 1;
             }
         }
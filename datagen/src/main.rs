@@ -9,8 +9,11 @@ fn main() {
         Some(s) => s.as_str(),
         None => "data.txt",
     };
+    let games: u32 = args
+        .get(2)
+        .map_or(7500, |x| x.parse::<u32>().unwrap_or(7500));
 
     let search_limits = vec![SearchLimit::Nodes(150_000), SearchLimit::Time(1000)];
     let mut data_generator = datagen::DataGenerator::new(search_limits, file_name);
-    data_generator.generate_data(7500);
+    data_generator.generate_data(games);
 }
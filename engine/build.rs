@@ -5,6 +5,7 @@ use std::io::BufWriter;
 use std::io::Write;
 use std::path::PathBuf;
 
+use build_script_dependencies::cuckoo_init::cuckoo_init_string;
 use build_script_dependencies::lmr_init::lmr_init_string;
 use build_script_dependencies::magic_table_builder::magic_table_init_string;
 use build_script_dependencies::trophism_init::trophism_table_init_string;
@@ -56,10 +57,34 @@ fn gen_output_file(name: &str, generator: fn() -> String) {
     write!(&mut out_file, "{}", generator()).unwrap();
 }
 
+// the net embedded with the "nnue" feature, empty unless EVALFILE is set
+fn embed_net() {
+    let mut out_dir: PathBuf = std::env::var("OUT_DIR").unwrap().into();
+    out_dir.push("net.bin");
+
+    match std::env::var("EVALFILE") {
+        Ok(path) if std::env::var("CARGO_FEATURE_NNUE").is_ok() => {
+            println!("cargo:rerun-if-changed={path}");
+            std::fs::copy(path, out_dir).expect("failed to read EVALFILE");
+        }
+        _ => {
+            File::create(out_dir).unwrap();
+        }
+    }
+}
+
 fn main() {
+    // watching EVALFILE turns off cargo's default of rerunning on any change in the package
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=build_script_dependencies");
+    println!("cargo:rerun-if-changed=3rdparty");
+    println!("cargo:rerun-if-env-changed=EVALFILE");
+
     gen_output_file("magic_lookup_init.rs", magic_table_init_string);
     gen_output_file("zobrist_keys_init.rs", zobrist_keys_init_string);
     gen_output_file("lmr_init.rs", lmr_init_string);
     gen_output_file("trophism_init.rs", trophism_table_init_string);
+    gen_output_file("cuckoo_init.rs", cuckoo_init_string);
     build_syzygy_code();
+    embed_net();
 }
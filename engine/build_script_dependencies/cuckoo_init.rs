@@ -0,0 +1,133 @@
+use crate::build_script_dependencies::dummy_types::{Bitboard, Square, NUM_SQUARES};
+use crate::build_script_dependencies::magic_table_builder::{
+    attacks_from_blockers, BISHOP_SHIFTERS, ROOK_SHIFTERS,
+};
+use crate::build_script_dependencies::zobrist_init::ZobristKeys;
+
+pub const CUCKOO_SIZE: usize = 8192;
+
+// must match the piece indices used by the engine
+const KNIGHT: usize = 0;
+const BISHOP: usize = 1;
+const ROOK: usize = 2;
+const QUEEN: usize = 3;
+const KING: usize = 5;
+
+const fn h1(key: u64) -> usize {
+    (key & 0x1fff) as usize
+}
+
+const fn h2(key: u64) -> usize {
+    ((key >> 16) & 0x1fff) as usize
+}
+
+fn leaper_attacks(sq: Square, offsets: &[(i8, i8)]) -> Bitboard {
+    let mut result = Bitboard::EMPTY;
+    for &(rank_offset, file_offset) in offsets {
+        let rank = sq.rank() as i8 + rank_offset;
+        let file = sq.file() as i8 + file_offset;
+        if (0..8).contains(&rank) && (0..8).contains(&file) {
+            result |= Square::new((rank * 8 + file) as u8).as_bitboard();
+        }
+    }
+    result
+}
+
+fn empty_board_attacks(piece: usize, sq: Square) -> Bitboard {
+    const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+        (2, 1),
+        (2, -1),
+        (-2, 1),
+        (-2, -1),
+        (1, 2),
+        (1, -2),
+        (-1, 2),
+        (-1, -2),
+    ];
+    const KING_OFFSETS: [(i8, i8); 8] = [
+        (1, 1),
+        (1, 0),
+        (1, -1),
+        (0, 1),
+        (0, -1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+    ];
+
+    let bishop = attacks_from_blockers(sq, Bitboard::EMPTY, BISHOP_SHIFTERS);
+    let rook = attacks_from_blockers(sq, Bitboard::EMPTY, ROOK_SHIFTERS);
+    match piece {
+        KNIGHT => leaper_attacks(sq, &KNIGHT_OFFSETS),
+        BISHOP => bishop,
+        ROOK => rook,
+        QUEEN => Bitboard::new(bishop.as_u64() | rook.as_u64()),
+        KING => leaper_attacks(sq, &KING_OFFSETS),
+        _ => panic!("Unexpected Piece!"),
+    }
+}
+
+// Marcel van Kervinck's cuckoo tables: every reversible (non-pawn) move is stored under
+// the zobrist difference it causes, so a repetition can be detected one move early.
+fn cuckoo_tables() -> ([u64; CUCKOO_SIZE], [u16; CUCKOO_SIZE]) {
+    let keys = ZobristKeys::generate();
+    let mut cuckoo_keys = [0; CUCKOO_SIZE];
+    let mut cuckoo_moves = [0; CUCKOO_SIZE];
+
+    let mut count = 0;
+    for color_keys in keys.pieces {
+        for piece in [KNIGHT, BISHOP, ROOK, QUEEN, KING] {
+            for s1 in 0..NUM_SQUARES {
+                for s2 in (s1 + 1)..NUM_SQUARES {
+                    let (sq_1, sq_2) = (Square::new(s1), Square::new(s2));
+                    if empty_board_attacks(piece, sq_1).as_u64() & sq_2.as_bitboard().as_u64() == 0
+                    {
+                        continue;
+                    }
+
+                    let mut mv = u16::from(s1) | (u16::from(s2) << 6);
+                    let mut key = color_keys[piece][s1 as usize]
+                        ^ color_keys[piece][s2 as usize]
+                        ^ keys.black_to_move;
+
+                    let mut i = h1(key);
+                    loop {
+                        std::mem::swap(&mut cuckoo_keys[i], &mut key);
+                        std::mem::swap(&mut cuckoo_moves[i], &mut mv);
+                        if mv == 0 {
+                            break;
+                        }
+                        i = if i == h1(key) { h2(key) } else { h1(key) };
+                    }
+                    count += 1;
+                }
+            }
+        }
+    }
+    assert_eq!(count, 3668, "Unexpected number of reversible moves");
+
+    (cuckoo_keys, cuckoo_moves)
+}
+
+pub fn cuckoo_init_string() -> String {
+    let (keys, moves) = cuckoo_tables();
+
+    let mut result = String::new();
+    result.push_str("CuckooTable {\nkeys: [");
+    for (i, key) in keys.iter().enumerate() {
+        if i % 8 == 0 {
+            result.push_str("\n  ");
+        }
+        result.push_str(format!("{key:#x}, ").as_str());
+    }
+    result.push_str("\n],\nmoves: [");
+    for (i, mv) in moves.iter().enumerate() {
+        if i % 16 == 0 {
+            result.push_str("\n  ");
+        }
+        result.push_str(format!("{mv}, ").as_str());
+    }
+    result.push_str("\n],\n}\n");
+
+    result
+}
@@ -210,13 +210,13 @@ impl MagicLookupBuilder {
 }
 
 type Shifter = fn(Bitboard) -> Bitboard;
-const ROOK_SHIFTERS: [Shifter; 4] = [
+pub const ROOK_SHIFTERS: [Shifter; 4] = [
     Bitboard::north_one,
     Bitboard::east_one,
     Bitboard::south_one,
     Bitboard::west_one,
 ];
-const BISHOP_SHIFTERS: [Shifter; 4] = [
+pub const BISHOP_SHIFTERS: [Shifter; 4] = [
     Bitboard::northeast_one,
     Bitboard::southeast_one,
     Bitboard::southwest_one,
@@ -238,7 +238,7 @@ fn calc_blocker_mask(sq: Square, shifters: [Shifter; 4]) -> Bitboard {
     result
 }
 
-fn attacks_from_blockers(sq: Square, blockers: Bitboard, shifters: [Shifter; 4]) -> Bitboard {
+pub fn attacks_from_blockers(sq: Square, blockers: Bitboard, shifters: [Shifter; 4]) -> Bitboard {
     let mut result = Bitboard::default();
     let availible = !blockers;
 
@@ -1,3 +1,4 @@
+pub mod cuckoo_init;
 mod dummy_types;
 pub mod lmr_init;
 pub mod magic_table_builder;
@@ -4,38 +4,69 @@ use crate::build_script_dependencies::rng::Rng;
 const NUM_CASTLING_CONFIGURATIONS: usize = 16;
 const NUM_FILES: usize = 8;
 
+pub struct ZobristKeys {
+    pub pieces: [[[u64; NUM_SQUARES as usize]; NUM_PIECES as usize]; NUM_COLORS as usize],
+    pub castling: [u64; NUM_CASTLING_CONFIGURATIONS],
+    pub ep_file: [u64; NUM_FILES],
+    pub black_to_move: u64,
+}
+
+impl ZobristKeys {
+    // the cuckoo tables depend on these, so both must be generated from the same sequence
+    pub fn generate() -> Self {
+        let mut rng = Rng::new();
+        let mut keys = Self {
+            pieces: [[[0; NUM_SQUARES as usize]; NUM_PIECES as usize]; NUM_COLORS as usize],
+            castling: [0; NUM_CASTLING_CONFIGURATIONS],
+            ep_file: [0; NUM_FILES],
+            black_to_move: 0,
+        };
+
+        keys.pieces
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(|k| *k = rng.rand_u64());
+        keys.castling.iter_mut().for_each(|k| *k = rng.rand_u64());
+        keys.ep_file.iter_mut().for_each(|k| *k = rng.rand_u64());
+        keys.black_to_move = rng.rand_u64();
+
+        keys
+    }
+}
+
 macro_rules! init_keys {
-    ($rng:ident, $table:ident, $count:ident) => {{
+    ($table:ident, $keys:expr) => {{
         $table.push_str("[");
-        for _ in 0..$count {
-            $table.push_str(format!("{:#x}, ", $rng.rand_u64()).as_str());
+        for key in $keys {
+            $table.push_str(format!("{:#x}, ", key).as_str());
         }
         $table.push_str("],\n");
     }};
 }
 
 pub fn zobrist_keys_init_string() -> String {
-    let mut rng = Rng::new();
+    let keys = ZobristKeys::generate();
     let mut table = String::new();
     table.push_str("ZobristKeys {\n");
 
     table.push_str("pieces: [\n");
-    for _ in 0..NUM_COLORS {
+    for color_keys in keys.pieces {
         table.push_str("[\n");
-        for _ in 0..NUM_PIECES {
-            init_keys!(rng, table, NUM_SQUARES);
+        for piece_keys in color_keys {
+            init_keys!(table, piece_keys);
         }
         table.push_str("],\n");
     }
     table.push_str("],\n");
 
     table.push_str("castling: ");
-    init_keys!(rng, table, NUM_CASTLING_CONFIGURATIONS);
+    init_keys!(table, keys.castling);
 
     table.push_str("ep_file: ");
-    init_keys!(rng, table, NUM_FILES);
+    init_keys!(table, keys.ep_file);
 
-    table.push_str(format!("black_to_move: {:#x},\n", rng.rand_u64()).as_str());
+    table.push_str(format!("black_to_move: {:#x},\n", keys.black_to_move).as_str());
 
     table.push_str("}\n");
 
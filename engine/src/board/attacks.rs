@@ -106,6 +106,21 @@ pub fn generic(piece: Piece, sq: Square, occupied: Bitboard) -> Bitboard {
     }
 }
 
+// squares strictly between two aligned squares, or empty if they don't share a line
+pub const fn between(a: Square, b: Square) -> Bitboard {
+    let (a_bb, b_bb) = (a.as_bitboard(), b.as_bitboard());
+    let rank_diff = a.rank().abs_diff(b.rank());
+    let file_diff = a.file().abs_diff(b.file());
+
+    if rank_diff == 0 || file_diff == 0 {
+        rook(a, b_bb).intersection(rook(b, a_bb))
+    } else if rank_diff == file_diff {
+        bishop(a, b_bb).intersection(bishop(b, a_bb))
+    } else {
+        Bitboard::EMPTY
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Bitboard, Square};
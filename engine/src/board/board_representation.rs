@@ -2,6 +2,8 @@ use super::chess_move::Move;
 use super::zobrist::ZobristHash;
 use super::zobrist_stack::ZobristStack;
 use super::{attacks, chess_move::Flag};
+#[cfg(feature = "nnue")]
+use crate::eval::nnue::{self, Accumulator, Network};
 use crate::tuple_constants_enum;
 use std::ops::{BitAnd, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
 
@@ -174,6 +176,17 @@ impl Square {
         self.0 % 8
     }
 
+    // the number of king moves between the squares
+    pub const fn distance(self, other: Self) -> u8 {
+        let rank_distance = self.rank().abs_diff(other.rank());
+        let file_distance = self.file().abs_diff(other.file());
+        if rank_distance > file_distance {
+            rank_distance
+        } else {
+            file_distance
+        }
+    }
+
     pub fn as_string(self) -> String {
         let col: Col = self.file();
         let row: Row = self.rank();
@@ -323,7 +336,7 @@ impl Bitboard {
         self.data.count_ones()
     }
 
-    const fn lsb(self) -> Square {
+    pub const fn lsb(self) -> Square {
         Square::new(self.data.trailing_zeros() as u8)
     }
 
@@ -622,6 +635,9 @@ pub struct Board {
     pub ep_sq: Option<Square>,
     pub castle_rights: CastleRights,
     pub halfmoves: u16,
+    pub pawn_hash: ZobristHash,
+    #[cfg(feature = "nnue")]
+    pub accumulator: Accumulator,
 }
 
 const fn fen_index_as_bitboard(i: u8) -> Bitboard {
@@ -718,10 +734,18 @@ impl Board {
         board.castle_rights = CastleRights::new(castle_data);
 
         board.ep_sq = Square::from_string(ep_sq);
+        board.pawn_hash = ZobristHash::pawns(&board);
+        #[cfg(feature = "nnue")]
+        board.set_network(nnue::active_network());
 
         board
     }
 
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, net: Option<&'static Network>) {
+        self.accumulator = Accumulator::new(self, net);
+    }
+
     #[allow(clippy::wrong_self_convention)]
     pub fn to_fen(&self) -> String {
         let mut pos = String::new();
@@ -831,6 +855,45 @@ impl Board {
         self.king_sq().is_attacked(self)
     }
 
+    // whether a pseudolegal move checks the enemy king, without playing it
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let color = self.color_to_move;
+        let king_sq = self.color_king_sq(color.flip());
+        let (from, to) = (mv.from(), mv.to());
+
+        // the piece that might give a direct check and where it ends up, and the squares
+        // our pieces leave
+        let (piece, sq, vacated) = match mv.flag() {
+            Flag::KS_CASTLE => (Piece::ROOK, from.right(1), from.right(3).as_bitboard()),
+            Flag::QS_CASTLE => (Piece::ROOK, from.left(1), from.left(4).as_bitboard()),
+            _ if mv.is_promo() => (mv.promo_piece(), to, Bitboard::EMPTY),
+            _ => (self.piece_on_sq(from), to, Bitboard::EMPTY),
+        };
+        let vacated = vacated | from.as_bitboard();
+        let mut occupied = (self.occupied() ^ vacated) | to.as_bitboard() | sq.as_bitboard();
+        if mv.flag() == Flag::EP {
+            occupied ^= Square::new(from.rank() * 8 + to.file()).as_bitboard();
+        }
+
+        let direct = match piece {
+            Piece::PAWN => attacks::pawn(sq, color),
+            Piece::KING => Bitboard::EMPTY,
+            _ => attacks::generic(piece, sq, occupied),
+        };
+        if direct.overlaps(king_sq.as_bitboard()) {
+            return true;
+        }
+
+        // a slider behind one of the vacated squares, or behind a pawn captured en passant
+        let queens = self.piece_bb(Piece::QUEEN, color);
+        let d_sliders = self.piece_bb(Piece::BISHOP, color) | queens;
+        let hv_sliders = self.piece_bb(Piece::ROOK, color) | queens;
+        (attacks::bishop(king_sq, occupied) & d_sliders)
+            .union(attacks::rook(king_sq, occupied) & hv_sliders)
+            .without(vacated)
+            .is_not_empty()
+    }
+
     pub const fn promotable_pawns(&self) -> Bitboard {
         let color = self.color_to_move;
         let pawns = self.piece_bb(Piece::PAWN, color);
@@ -970,6 +1033,22 @@ impl Board {
             return false;
         }
 
+        if piece == Piece::PAWN || piece == Piece::KING {
+            self.pawn_hash.hash_piece(color, piece, from_sq);
+            if !mv.is_promo() {
+                self.pawn_hash.hash_piece(color, piece, to_sq);
+            }
+        }
+        if captured_piece == Piece::PAWN {
+            self.pawn_hash.hash_piece(opp_color, Piece::PAWN, to_sq);
+        } else if flag == Flag::EP {
+            self.pawn_hash.hash_piece(opp_color, Piece::PAWN, to_sq.row_swap());
+        }
+        debug_assert_eq!(self.pawn_hash, ZobristHash::pawns(self));
+
+        #[cfg(feature = "nnue")]
+        self.update_accumulator(mv, piece, captured_piece);
+
         self.castle_rights.update(mv);
         hash_base.hash_castling(self.castle_rights);
 
@@ -981,6 +1060,46 @@ impl Board {
         true
     }
 
+    #[cfg(feature = "nnue")]
+    fn update_accumulator(&mut self, mv: Move, piece: Piece, captured_piece: Piece) {
+        if !self.accumulator.is_active() {
+            return;
+        }
+
+        let color = self.color_to_move;
+        let opp_color = color.flip();
+        let (from_sq, to_sq) = (mv.from(), mv.to());
+        let acc = &mut self.accumulator;
+
+        if captured_piece != Piece::NONE {
+            acc.remove(opp_color, captured_piece, to_sq);
+        }
+
+        let to_piece = if mv.is_promo() {
+            mv.promo_piece()
+        } else {
+            piece
+        };
+        acc.move_piece(color, (piece, from_sq), (to_piece, to_sq));
+
+        match mv.flag() {
+            Flag::KS_CASTLE => acc.move_piece(
+                color,
+                (Piece::ROOK, from_sq.right(3)),
+                (Piece::ROOK, from_sq.right(1)),
+            ),
+            Flag::QS_CASTLE => acc.move_piece(
+                color,
+                (Piece::ROOK, from_sq.left(4)),
+                (Piece::ROOK, from_sq.left(1)),
+            ),
+            Flag::EP => acc.remove(opp_color, Piece::PAWN, to_sq.row_swap()),
+            _ => (),
+        }
+
+        debug_assert!(self.accumulator == Accumulator::new(self, self.accumulator.network()));
+    }
+
     pub fn simple_try_play_move(&mut self, mv: Move) -> bool {
         let mut dummy_stack = ZobristStack::new(self);
         let dummy_base = ZobristHash::incremental_update_base(self);
@@ -991,7 +1110,7 @@ impl Board {
         let null_base = ZobristHash::nullmove_base(self);
         self.color_to_move = self.color_to_move.flip();
         self.ep_sq = None;
-        zobrist_stack.add_hash(null_base);
+        zobrist_stack.add_null_hash(null_base);
     }
 
     pub const fn fifty_move_draw(&self) -> bool {
@@ -1052,8 +1171,43 @@ impl Board {
 
 #[cfg(test)]
 mod tests {
-    use super::{Bitboard, Board, Color, Square, START_FEN};
-    use crate::{bb_from_squares, board::board_representation::CastleRights};
+    use super::{Bitboard, Board, Color, Square, ZobristHash, START_FEN};
+    use crate::{
+        bb_from_squares,
+        board::{board_representation::CastleRights, movegen::MoveGenerator},
+    };
+
+    #[test]
+    fn gives_check_matches_playing_the_move() {
+        let fens = [
+            START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            // en passant and castling that uncover or give check
+            "8/8/8/k1pP3R/8/8/8/7K w - c6 0 1",
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+            "3k4/8/8/8/8/8/8/R3K3 w Q - 0 1",
+            "8/8/8/8/1k6/8/2P5/3B2K1 w - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::from_fen(fen);
+            let mut generator = MoveGenerator::new();
+            while let Some(mv) = generator.simple_next::<true>(&board) {
+                let mut next_board = board.clone();
+                if next_board.simple_try_play_move(mv) {
+                    assert_eq!(
+                        board.gives_check(mv),
+                        next_board.in_check(),
+                        "{fen} {}",
+                        mv.as_string()
+                    );
+                }
+            }
+        }
+    }
 
     #[test]
     fn bit_and_works() {
@@ -1149,6 +1303,9 @@ mod tests {
             castle_rights: CastleRights::new(0b1111),
             ep_sq: None,
             halfmoves: 0,
+            pawn_hash: ZobristHash::pawns(&actual),
+            #[cfg(feature = "nnue")]
+            accumulator: actual.accumulator.clone(),
         };
 
         assert_eq!(actual, expected);
@@ -46,6 +46,14 @@ impl Move {
         self.data == 0
     }
 
+    pub const fn as_u16(self) -> u16 {
+        self.data
+    }
+
+    pub const fn from_u16(data: u16) -> Self {
+        Self { data }
+    }
+
     pub const fn new(to: Square, from: Square, flag: Flag) -> Self {
         Self {
             data: to.as_u16() | (from.as_u16() << Self::FROM_OFFSET) | flag.0,
@@ -16,7 +16,25 @@ struct ZobristKeys {
 
 const ZOBRIST_KEYS: ZobristKeys = include!(concat!(env!("OUT_DIR"), "/zobrist_keys_init.rs"));
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+const CUCKOO_SIZE: usize = 8192;
+struct CuckooTable {
+    keys: [u64; CUCKOO_SIZE],
+    moves: [u16; CUCKOO_SIZE],
+}
+
+static CUCKOO_TABLE: CuckooTable = include!(concat!(env!("OUT_DIR"), "/cuckoo_init.rs"));
+
+impl CuckooTable {
+    const fn h1(key: u64) -> usize {
+        (key & 0x1fff) as usize
+    }
+
+    const fn h2(key: u64) -> usize {
+        ((key >> 16) & 0x1fff) as usize
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct ZobristHash(u64);
 
 impl ZobristHash {
@@ -61,6 +79,21 @@ impl ZobristHash {
         hash
     }
 
+    // only the pawns and kings, which is the key of the pawn hash table
+    pub fn pawns(board: &Board) -> Self {
+        let mut hash = Self(0);
+        for color in Color::LIST {
+            for piece in [Piece::PAWN, Piece::KING] {
+                let mut piece_bb = board.piece_bb(piece, color);
+                bitloop!(|sq| piece_bb, {
+                    hash.hash_piece(color, piece, sq);
+                });
+            }
+        }
+
+        hash
+    }
+
     pub const fn incremental_update_base(board: &Board) -> Self {
         let mut hash: u64 = ZOBRIST_KEYS.black_to_move;
 
@@ -83,6 +116,21 @@ impl ZobristHash {
         Self(hash)
     }
 
+    // returns the squares of the reversible move that changes the hash by this difference, if any
+    pub fn cuckoo_move(self) -> Option<(Square, Square)> {
+        let key = self.0;
+        let index = if CUCKOO_TABLE.keys[CuckooTable::h1(key)] == key {
+            CuckooTable::h1(key)
+        } else if CUCKOO_TABLE.keys[CuckooTable::h2(key)] == key {
+            CuckooTable::h2(key)
+        } else {
+            return None;
+        };
+
+        let mv = CUCKOO_TABLE.moves[index];
+        Some((Square::new((mv & 0x3f) as u8), Square::new((mv >> 6) as u8)))
+    }
+
     pub const fn as_u64(self) -> u64 {
         self.0
     }
@@ -1,14 +1,18 @@
-use super::{board_representation::Board, zobrist::ZobristHash};
+use super::{attacks, board_representation::Board, zobrist::ZobristHash};
+use crate::search::search::Ply;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ZobristStack {
     zobrist_vec: Vec<ZobristHash>,
+    // the index of each position reached by a null move
+    null_indices: Vec<usize>,
 }
 
 impl ZobristStack {
     pub fn new(board: &Board) -> Self {
         Self {
             zobrist_vec: vec![ZobristHash::complete(board)],
+            null_indices: vec![],
         }
     }
 
@@ -17,30 +21,96 @@ impl ZobristStack {
         self.zobrist_vec.push(new_hash);
     }
 
+    pub fn add_null_hash(&mut self, null_base: ZobristHash) {
+        self.add_hash(null_base);
+        self.null_indices.push(self.zobrist_vec.len() - 1);
+    }
+
     pub fn revert_state(&mut self) {
+        if self.null_indices.last() == Some(&(self.zobrist_vec.len() - 1)) {
+            self.null_indices.pop();
+        }
         self.zobrist_vec.pop();
     }
 
+    // positions before a null move can't be reached again by real moves
+    fn plies_from_null(&self) -> usize {
+        let current = self.zobrist_vec.len() - 1;
+        self.null_indices
+            .last()
+            .map_or(current, |&index| current - index)
+    }
+
     pub fn current_zobrist_hash(&self) -> ZobristHash {
         let len = self.zobrist_vec.len();
         self.zobrist_vec[len - 1]
     }
 
     pub fn twofold_repetition(&self, halfmoves: u16) -> bool {
+        self.repetition_distance(halfmoves).is_some()
+    }
+
+    // how many plies back the current position last occurred
+    pub fn repetition_distance(&self, halfmoves: u16) -> Option<usize> {
         if self.zobrist_vec.len() < 4 {
-            return false;
+            return None;
         }
 
         let current_hash = self.current_zobrist_hash();
-        for &hash in self
-            .zobrist_vec
+        self.zobrist_vec
             .iter()
             .rev()
             .take((halfmoves + 1) as usize)
+            .enumerate()
             .skip(2)
             .step_by(2)
-        {
-            if hash == current_hash {
+            .find_map(|(distance, &hash)| (hash == current_hash).then_some(distance))
+    }
+
+    // Detects whether the side to move has a reversible move that returns to an earlier
+    // position, using the cuckoo tables (Marcel van Kervinck's method, as in Stockfish).
+    pub fn upcoming_repetition(&self, board: &Board, ply: Ply) -> bool {
+        let end = usize::from(board.halfmoves).min(self.plies_from_null());
+        if end < 3 {
+            return false;
+        }
+
+        let current_hash = self.current_zobrist_hash();
+        let occupied = board.occupied();
+        for i in (3..=end).step_by(2) {
+            let old_hash = self.zobrist_vec[self.zobrist_vec.len() - 1 - i];
+            let Some((sq_1, sq_2)) = current_hash.combine(old_hash).cuckoo_move() else {
+                continue;
+            };
+
+            if attacks::between(sq_1, sq_2).overlaps(occupied) {
+                continue;
+            }
+
+            if usize::from(ply) > i {
+                return true;
+            }
+
+            // before the root, the move must actually be playable by the side to move, as
+            // both directions of a move share a single cuckoo entry
+            let piece_sq = if occupied.overlaps(sq_1.as_bitboard()) {
+                sq_1
+            } else {
+                sq_2
+            };
+            if !board.us().overlaps(piece_sq.as_bitboard()) {
+                continue;
+            }
+
+            // and the position it returns to must have already repeated in the game
+            let index = self.zobrist_vec.len() - 1 - i;
+            let first = self.zobrist_vec.len() - 1 - end;
+            if (first..index)
+                .rev()
+                .skip(3)
+                .step_by(2)
+                .any(|j| self.zobrist_vec[j] == old_hash)
+            {
                 return true;
             }
         }
@@ -96,6 +166,61 @@ mod tests {
         );
 
         assert!(zobrist_stack.twofold_repetition(board.halfmoves));
+        assert_eq!(zobrist_stack.repetition_distance(board.halfmoves), Some(4));
+    }
+
+    #[test]
+    fn upcoming_repetition_works() {
+        use crate::board::board_representation::START_FEN;
+
+        let mut board = Board::from_fen(START_FEN);
+        let mut zobrist_stack = ZobristStack::new(&board);
+
+        for mv in ["g1f3", "g8f6"] {
+            let mv = Move::from_string(mv, &board);
+            let hash_base = ZobristHash::incremental_update_base(&board);
+            board.try_play_move(mv, &mut zobrist_stack, hash_base);
+        }
+        assert!(!zobrist_stack.upcoming_repetition(&board, 0));
+
+        // black can now return to the starting position with Ng8
+        let mv = Move::from_string("f3g1", &board);
+        let hash_base = ZobristHash::incremental_update_base(&board);
+        board.try_play_move(mv, &mut zobrist_stack, hash_base);
+        assert!(zobrist_stack.upcoming_repetition(&board, 4));
+
+        // before the root, only once the starting position has already repeated
+        assert!(!zobrist_stack.upcoming_repetition(&board, 0));
+        for mv in ["f6g8", "g1f3", "g8f6", "f3g1"] {
+            let mv = Move::from_string(mv, &board);
+            let hash_base = ZobristHash::incremental_update_base(&board);
+            board.try_play_move(mv, &mut zobrist_stack, hash_base);
+        }
+        assert!(zobrist_stack.upcoming_repetition(&board, 0));
+    }
+
+    #[test]
+    fn upcoming_repetition_stops_at_null_moves() {
+        let mut board = Board::from_fen("7k/8/8/8/8/8/8/R6K w - - 0 1");
+        let mut zobrist_stack = ZobristStack::new(&board);
+
+        let play = |board: &mut Board, zobrist_stack: &mut ZobristStack, mv: &str| {
+            let mv = Move::from_string(mv, board);
+            let hash_base = ZobristHash::incremental_update_base(board);
+            board.try_play_move(mv, zobrist_stack, hash_base);
+        };
+        play(&mut board, &mut zobrist_stack, "h1g1");
+        play(&mut board, &mut zobrist_stack, "h8g8");
+
+        // Ra3, null, Ra5 differs from the position before by the a1-a5 cuckoo move
+        play(&mut board, &mut zobrist_stack, "a1a3");
+        board.play_nullmove(&mut zobrist_stack);
+        play(&mut board, &mut zobrist_stack, "a3a5");
+        assert!(!zobrist_stack.upcoming_repetition(&board, 10));
+
+        zobrist_stack.revert_state();
+        zobrist_stack.revert_state();
+        assert!(zobrist_stack.null_indices.is_empty());
     }
 
     #[test]
@@ -0,0 +1,194 @@
+use std::sync::OnceLock;
+
+use crate::{
+    bitloop,
+    board::{
+        attacks,
+        board_representation::{Color, Square},
+    },
+};
+
+// King and pawn vs king, seen from the side with the pawn as white with its pawn on files a to d.
+// Every position is indexed by the side to move, both kings and the pawn, which is on ranks 2 to 7.
+const MAX_INDEX: usize = 2 * 24 * 64 * 64;
+
+// results as bits, so that the results of all the moves from a position can be or'ed together
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+// one bit per position, set if white wins
+static KPK: OnceLock<Vec<u64>> = OnceLock::new();
+
+fn index(stm: Color, black_king: Square, white_king: Square, pawn: Square) -> usize {
+    white_king.as_index()
+        | black_king.as_index() << 6
+        | stm.as_index() << 12
+        | usize::from(pawn.file()) << 13
+        | usize::from(6 - pawn.rank()) << 15
+}
+
+const fn decode(index: usize) -> (Color, Square, Square, Square) {
+    let stm = if (index >> 12) & 1 == 0 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let black_king = Square::new(((index >> 6) & 63) as u8);
+    let white_king = Square::new((index & 63) as u8);
+    let pawn_rank = 6 - (index >> 15) as u8;
+    let pawn = Square::new(pawn_rank * 8 + ((index >> 13) & 3) as u8);
+
+    (stm, black_king, white_king, pawn)
+}
+
+const fn north(sq: Square) -> Square {
+    Square::new(sq.as_index() as u8 + 8)
+}
+
+// the positions that are decided without looking at any moves
+fn initial_result(stm: Color, black_king: Square, white_king: Square, pawn: Square) -> u8 {
+    let pawn_attacks = attacks::pawn(pawn, Color::White);
+
+    if white_king.distance(black_king) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (stm == Color::White && pawn_attacks.overlaps(black_king.as_bitboard()))
+    {
+        return INVALID;
+    }
+
+    // the pawn promotes without being captured
+    if stm == Color::White
+        && pawn.rank() == 6
+        && white_king != north(pawn)
+        && (black_king.distance(north(pawn)) > 1 || white_king.distance(north(pawn)) == 1)
+    {
+        return WIN;
+    }
+
+    // stalemate, or the pawn is lost
+    let black_king_moves = attacks::king(black_king);
+    let covered = attacks::king(white_king).union(pawn_attacks);
+    if stm == Color::Black
+        && (black_king_moves.without(covered).is_empty()
+            || black_king_moves
+                .intersection(pawn.as_bitboard())
+                .without(attacks::king(white_king))
+                .is_not_empty())
+    {
+        return DRAW;
+    }
+
+    UNKNOWN
+}
+
+fn classify(db: &[u8], stm: Color, black_king: Square, white_king: Square, pawn: Square) -> u8 {
+    let (good, bad) = match stm {
+        Color::White => (WIN, DRAW),
+        Color::Black => (DRAW, WIN),
+    };
+
+    let mut result = INVALID;
+    match stm {
+        Color::White => {
+            let mut moves = attacks::king(white_king);
+            bitloop!(|sq| moves, {
+                result |= db[index(Color::Black, black_king, sq, pawn)];
+            });
+
+            // promotions are immediate wins or not worth it, so only pushes below the 7th rank
+            if pawn.rank() < 6 {
+                let push = north(pawn);
+                result |= db[index(Color::Black, black_king, white_king, push)];
+
+                if pawn.rank() == 1 && push != white_king && push != black_king {
+                    result |= db[index(Color::Black, black_king, white_king, north(push))];
+                }
+            }
+        }
+        Color::Black => {
+            let mut moves = attacks::king(black_king);
+            bitloop!(|sq| moves, {
+                result |= db[index(Color::White, sq, white_king, pawn)];
+            });
+        }
+    }
+
+    if result & good != 0 {
+        good
+    } else if result & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+// retrograde analysis, until every position is a win, a draw or invalid
+fn generate() -> Vec<u64> {
+    let mut db = (0..MAX_INDEX)
+        .map(|i| {
+            let (stm, black_king, white_king, pawn) = decode(i);
+            initial_result(stm, black_king, white_king, pawn)
+        })
+        .collect::<Vec<_>>();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..MAX_INDEX {
+            if db[i] == UNKNOWN {
+                let (stm, black_king, white_king, pawn) = decode(i);
+                let result = classify(&db, stm, black_king, white_king, pawn);
+                if result != UNKNOWN {
+                    db[i] = result;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let mut bits = vec![0; MAX_INDEX / 64];
+    for (i, &result) in db.iter().enumerate() {
+        if result == WIN {
+            bits[i / 64] |= 1 << (i % 64);
+        }
+    }
+    bits
+}
+
+// whether the side with the pawn wins, generated on first use
+pub fn kpk_is_win(
+    strong: Color,
+    stm: Color,
+    strong_king: Square,
+    pawn: Square,
+    weak_king: Square,
+) -> bool {
+    let normalize = |sq: Square| {
+        let sq = if strong == Color::Black {
+            sq.mirror()
+        } else {
+            sq
+        };
+        if pawn.file() >= 4 {
+            sq.y_mirror()
+        } else {
+            sq
+        }
+    };
+    let stm = if stm == strong {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    let i = index(
+        stm,
+        normalize(weak_king),
+        normalize(strong_king),
+        normalize(pawn),
+    );
+    KPK.get_or_init(generate)[i / 64] & (1 << (i % 64)) != 0
+}
@@ -0,0 +1,455 @@
+use std::sync::OnceLock;
+
+use crate::{
+    board::board_representation::{Bitboard, Board, Color, Piece, Square},
+    eval::{
+        bitbase::kpk_is_win,
+        evaluation::{EvalScore, Phase},
+    },
+};
+
+// the endgame part of the eval is multiplied by a scale factor out of this
+pub const SCALE_NORMAL: i32 = 64;
+
+// far above any normal eval, but below the tablebase scores
+const KNOWN_WIN: EvalScore = 10000;
+
+const PAWN_VALUE: EvalScore = 100;
+const ROOK_VALUE: EvalScore = 500;
+const QUEEN_VALUE: EvalScore = 950;
+
+// none of the specialized evaluators need more pieces than this, kings included
+const MAX_ENDGAME_PIECES: u32 = 4;
+
+// The number of each piece of each color
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MaterialKey(u64);
+
+impl MaterialKey {
+    pub fn new(board: &Board) -> Self {
+        Self::from_counts(|color, piece| board.piece_bb(piece, color).popcount().into())
+    }
+
+    // from a signature such as "KRKP", where the pieces after the first king belong to strong
+    fn from_code(code: &str, strong: Color) -> Self {
+        let weak_start = code[1..].find('K').unwrap() + 1;
+        let (strong_pieces, weak_pieces) = code.split_at(weak_start);
+
+        Self::from_counts(|color, piece| {
+            let pieces = if color == strong {
+                strong_pieces
+            } else {
+                weak_pieces
+            };
+            pieces
+                .chars()
+                .filter(|&ch| Piece::from_char(ch) == Some(piece))
+                .count() as u64
+        })
+    }
+
+    fn from_counts(count: impl Fn(Color, Piece) -> u64) -> Self {
+        let mut key = 0;
+        for color in Color::LIST {
+            for piece in Piece::LIST {
+                key = (key << 4) | count(color, piece);
+            }
+        }
+        Self(key)
+    }
+}
+
+// Material signatures with an evaluator of their own, which replaces the general eval
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Endgame {
+    Kpk,
+    Kbnk,
+    Knnk,
+    Krkp,
+    Kqkp,
+    Krkb,
+    Krkn,
+}
+
+static ENDGAMES: OnceLock<Vec<(MaterialKey, Color, Endgame)>> = OnceLock::new();
+
+const fn push_close(a: Square, b: Square) -> EvalScore {
+    140 - 20 * a.distance(b) as EvalScore
+}
+
+const fn push_away(a: Square, b: Square) -> EvalScore {
+    120 - push_close(a, b)
+}
+
+const fn edge_distance(x: u8) -> EvalScore {
+    if x < 4 {
+        x as EvalScore
+    } else {
+        7 - x as EvalScore
+    }
+}
+
+const fn push_to_edge(sq: Square) -> EvalScore {
+    let (rank, file) = (edge_distance(sq.rank()), edge_distance(sq.file()));
+    90 - (7 * file * file / 2 + 7 * rank * rank / 2)
+}
+
+// towards a1 and h8
+const fn push_to_dark_corner(sq: Square) -> EvalScore {
+    (7 - sq.rank() as EvalScore - sq.file() as EvalScore).abs()
+}
+
+impl Endgame {
+    pub const LIST: [(Self, &'static str); 7] = [
+        (Self::Kpk, "KPK"),
+        (Self::Kbnk, "KBNK"),
+        (Self::Knnk, "KNNK"),
+        (Self::Krkp, "KRKP"),
+        (Self::Kqkp, "KQKP"),
+        (Self::Krkb, "KRKB"),
+        (Self::Krkn, "KRKN"),
+    ];
+
+    pub fn name(self) -> &'static str {
+        Self::LIST
+            .iter()
+            .find(|(endgame, _)| *endgame == self)
+            .unwrap()
+            .1
+    }
+
+    // the endgame of the position and the side it is named after, if there is one
+    pub fn probe(board: &Board) -> Option<(Self, Color)> {
+        if board.occupied().popcount() > MAX_ENDGAME_PIECES {
+            return None;
+        }
+
+        let key = MaterialKey::new(board);
+        ENDGAMES
+            .get_or_init(|| {
+                Self::LIST
+                    .iter()
+                    .flat_map(|&(endgame, code)| {
+                        Color::LIST
+                            .map(|strong| (MaterialKey::from_code(code, strong), strong, endgame))
+                    })
+                    .collect()
+            })
+            .iter()
+            .find(|(endgame_key, ..)| *endgame_key == key)
+            .map(|&(_, strong, endgame)| (endgame, strong))
+    }
+
+    // from the strong side's point of view
+    fn evaluate(self, board: &Board, strong: Color) -> EvalScore {
+        let weak = strong.flip();
+        let square = |piece: Piece, color: Color| board.piece_bb(piece, color).lsb();
+        let strong_king = board.color_king_sq(strong);
+        let weak_king = board.color_king_sq(weak);
+
+        match self {
+            Self::Kpk => {
+                let pawn = square(Piece::PAWN, strong);
+                if kpk_is_win(strong, board.color_to_move, strong_king, pawn, weak_king) {
+                    let rank = match strong {
+                        Color::White => pawn.rank(),
+                        Color::Black => 7 - pawn.rank(),
+                    };
+                    KNOWN_WIN + PAWN_VALUE + EvalScore::from(rank)
+                } else {
+                    0
+                }
+            }
+            Self::Kbnk => {
+                // mate can only be forced in the corners of the bishop's color
+                let bishop = square(Piece::BISHOP, strong);
+                let weak_king = if bishop.as_bitboard().overlaps(Bitboard::DARK_SQ) {
+                    weak_king
+                } else {
+                    weak_king.y_mirror()
+                };
+                KNOWN_WIN + push_close(strong_king, weak_king) + 50 * push_to_dark_corner(weak_king)
+            }
+            Self::Knnk => 0,
+            Self::Krkp => krkp(board, strong),
+            Self::Kqkp => {
+                // a bishop or rook pawn on the 7th supported by its king can hold the draw
+                let pawn = square(Piece::PAWN, weak);
+                let relative_rank = match weak {
+                    Color::White => pawn.rank(),
+                    Color::Black => 7 - pawn.rank(),
+                };
+                let mut eval = push_close(strong_king, weak_king);
+                if relative_rank != 6
+                    || weak_king.distance(pawn) != 1
+                    || ![0, 2, 5, 7].contains(&pawn.file())
+                {
+                    eval += QUEEN_VALUE - PAWN_VALUE;
+                }
+                eval
+            }
+            // usually drawn, but the weak side has to be careful near the edge
+            Self::Krkb => push_to_edge(weak_king),
+            Self::Krkn => {
+                push_to_edge(weak_king) + push_away(weak_king, square(Piece::KNIGHT, weak))
+            }
+        }
+    }
+}
+
+fn krkp(board: &Board, strong: Color) -> EvalScore {
+    let weak = strong.flip();
+    // seen from the strong side as white, so the pawn moves down the board
+    let relative = |sq: Square| match strong {
+        Color::White => sq,
+        Color::Black => sq.mirror(),
+    };
+    let square = |piece: Piece, color: Color| relative(board.piece_bb(piece, color).lsb());
+    let strong_king = square(Piece::KING, strong);
+    let weak_king = square(Piece::KING, weak);
+    let rook = square(Piece::ROOK, strong);
+    let pawn = square(Piece::PAWN, weak);
+    let queening_sq = Square::new(pawn.file());
+    let weak_to_move = EvalScore::from(board.color_to_move == weak);
+    let strong_to_move = 1 - weak_to_move;
+    let distance = |a: Square, b: Square| EvalScore::from(a.distance(b));
+
+    if strong_king.file() == pawn.file() && strong_king.rank() < pawn.rank() {
+        // our king is in front of the pawn
+        ROOK_VALUE - distance(strong_king, pawn)
+    } else if distance(weak_king, pawn) >= 3 + weak_to_move && distance(weak_king, rook) >= 3 {
+        // their king is too far from both the pawn and the rook
+        ROOK_VALUE - distance(strong_king, pawn)
+    } else if weak_king.rank() <= 2
+        && distance(weak_king, pawn) == 1
+        && strong_king.rank() >= 3
+        && distance(strong_king, pawn) > 2 + strong_to_move
+    {
+        // an advanced pawn supported by its king while ours is far away
+        40 - 4 * distance(strong_king, pawn)
+    } else {
+        let stop = Square::new(pawn.as_index() as u8 - 8);
+        100 - 4
+            * (distance(strong_king, stop)
+                - distance(weak_king, stop)
+                - distance(pawn, queening_sq))
+    }
+}
+
+// the specialized eval of the position from the side to move's point of view, if there is one
+pub fn evaluate(board: &Board) -> Option<EvalScore> {
+    let (endgame, strong) = Endgame::probe(board)?;
+    let eval = endgame.evaluate(board, strong);
+    Some(if strong == board.color_to_move {
+        eval
+    } else {
+        -eval
+    })
+}
+
+// knights and bishops count as 1, rooks as 2 and queens as 4, like the phase
+fn non_pawn_material(board: &Board, color: Color) -> i32 {
+    let count = |piece: Piece| board.piece_bb(piece, color).popcount().cast_signed();
+    count(Piece::KNIGHT) + count(Piece::BISHOP) + 2 * count(Piece::ROOK) + 4 * count(Piece::QUEEN)
+}
+
+const fn opposite_bishops(board: &Board) -> bool {
+    let w_bishops = board.piece_bb(Piece::BISHOP, Color::White);
+    let b_bishops = board.piece_bb(Piece::BISHOP, Color::Black);
+
+    let light_sq_bishops = w_bishops.union(b_bishops).intersection(Bitboard::LIGHT_SQ);
+
+    w_bishops.popcount() == 1 && b_bishops.popcount() == 1 && light_sq_bishops.popcount() == 1
+}
+
+// rook pawns and a bishop that can't control the queening square against a king in the corner
+fn wrong_bishop(board: &Board, strong: Color) -> bool {
+    let weak = strong.flip();
+    let pawns = board.piece_bb(Piece::PAWN, strong);
+    let bishops = board.piece_bb(Piece::BISHOP, strong);
+    let strong_pieces = board.all[strong.as_index()];
+    let weak_pieces = board.all[weak.as_index()];
+    if bishops.popcount() != 1
+        || pawns.is_empty()
+        || strong_pieces != pawns | bishops | board.piece_bb(Piece::KING, strong)
+        || weak_pieces != board.piece_bb(Piece::KING, weak)
+    {
+        return false;
+    }
+
+    let file = if pawns.without(Bitboard::A_FILE).is_empty() {
+        0
+    } else if pawns.without(Bitboard::H_FILE).is_empty() {
+        7
+    } else {
+        return false;
+    };
+    let queening_sq = match strong {
+        Color::White => Square::new(56 + file),
+        Color::Black => Square::new(file),
+    };
+    let light = |bb: Bitboard| bb.overlaps(Bitboard::LIGHT_SQ);
+
+    light(bishops) != light(queening_sq.as_bitboard())
+        && board.color_king_sq(weak).distance(queening_sq) <= 1
+}
+
+// How much of the endgame part of the eval the side that is ahead keeps, out of SCALE_NORMAL
+pub fn scale_factor(board: &Board, strong: Color, phase: Phase) -> i32 {
+    let weak = strong.flip();
+    let strong_pawns = board.piece_bb(Piece::PAWN, strong).popcount().cast_signed();
+    let weak_pawns = board.piece_bb(Piece::PAWN, weak).popcount().cast_signed();
+
+    if phase == 2 && opposite_bishops(board) {
+        return SCALE_NORMAL / 4;
+    }
+
+    if wrong_bishop(board, strong) {
+        return 0;
+    }
+
+    // without pawns, being up no more than a minor piece is rarely enough
+    let (strong_material, weak_material) = (
+        non_pawn_material(board, strong),
+        non_pawn_material(board, weak),
+    );
+    if strong_pawns == 0 && strong_material - weak_material <= 1 {
+        return if strong_material < 2 {
+            0
+        } else if weak_material <= 1 {
+            4
+        } else {
+            14
+        };
+    }
+
+    // fewer pawns leave fewer ways to win
+    let mut scale = SCALE_NORMAL.min(36 + 7 * strong_pawns);
+
+    // so do pawns on a single wing without much of an advantage
+    let pawns = board.pieces[Piece::PAWN.as_index()];
+    let queenside = Bitboard::A_FILE.file_fill()
+        | Bitboard::A_FILE.shift_east(1)
+        | Bitboard::A_FILE.shift_east(2)
+        | Bitboard::A_FILE.shift_east(3);
+    let one_wing = pawns.without(queenside).is_empty() || pawns.intersection(queenside).is_empty();
+    if one_wing && strong_pawns <= weak_pawns + 1 {
+        scale = scale.min(48);
+    }
+
+    scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, scale_factor, Endgame, KNOWN_WIN, SCALE_NORMAL};
+    use crate::{
+        board::board_representation::{Board, Color},
+        eval::evaluation::{evaluate_hce, phase},
+    };
+
+    fn eval(fen: &str) -> i32 {
+        evaluate(&Board::from_fen(fen)).unwrap()
+    }
+
+    #[test]
+    fn dispatches_on_material() {
+        let probe = |fen| Endgame::probe(&Board::from_fen(fen));
+        assert_eq!(
+            probe("R7/8/8/8/2p4k/8/2K5/8 w - - 0 1"),
+            Some((Endgame::Krkp, Color::White))
+        );
+        assert_eq!(
+            probe("r7/8/8/8/2P4K/8/2k5/8 w - - 0 1"),
+            Some((Endgame::Krkp, Color::Black))
+        );
+        assert_eq!(
+            probe("8/8/8/4k3/8/8/8/KBN5 w - - 0 1"),
+            Some((Endgame::Kbnk, Color::White))
+        );
+        assert_eq!(probe("8/8/8/4k3/8/8/8/KBB5 w - - 0 1"), None);
+        assert_eq!(
+            probe("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            None
+        );
+    }
+
+    #[test]
+    fn kpk_bitbase() {
+        // whoever has to move loses the opposition
+        assert_eq!(eval("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"), 0);
+        assert!(eval("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1") < -KNOWN_WIN);
+        // the king on the 6th in front of its pawn always wins
+        assert!(eval("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1") > KNOWN_WIN);
+        // outside the square of the pawn
+        assert!(eval("k7/8/8/8/8/8/7P/7K w - - 0 1") > KNOWN_WIN);
+        // the rook pawn can't get the king out of the corner
+        assert_eq!(eval("k7/8/8/8/8/8/P7/K7 w - - 0 1"), 0);
+        // and the same for black
+        assert!(eval("4k3/4p3/8/8/8/8/8/K7 b - - 0 1") > KNOWN_WIN);
+        assert_eq!(eval("8/8/8/4p3/4k3/8/4K3/8 b - - 0 1"), 0);
+        assert_eq!(
+            evaluate_hce(&Board::from_fen("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1")),
+            0
+        );
+    }
+
+    #[test]
+    fn kbnk_drives_to_the_bishops_corner() {
+        // light squared bishop, so a8 and h1 are the mating corners
+        let right_corner = eval("8/8/8/8/3KBN2/8/8/7k w - - 0 1");
+        let wrong_corner = eval("8/8/8/8/3KBN2/8/8/k7 w - - 0 1");
+        assert!(wrong_corner > KNOWN_WIN);
+        assert!(right_corner > wrong_corner);
+    }
+
+    #[test]
+    fn rook_and_queen_vs_pawn() {
+        // the king in front of the pawn wins
+        assert!(eval("R7/8/8/8/2p4k/8/2K5/8 w - - 0 1") > 400);
+        // an advanced pawn supported by its king, with ours far away
+        assert!(eval("7R/8/6K1/8/8/2k5/2p5/8 w - - 0 1") < 50);
+        // a bishop pawn on the 7th with its king next to it holds
+        assert!(eval("Q7/8/8/8/8/8/2p5/1k5K w - - 0 1") < 200);
+        assert!(eval("Q7/8/8/8/8/8/3p4/4k2K w - - 0 1") > 700);
+    }
+
+    #[test]
+    fn drawish_endgames() {
+        assert!(eval("8/8/8/3k4/8/8/8/R3K2b w - - 0 1").abs() < 100);
+        assert!(eval("8/8/8/3k4/8/8/8/R3K2n w - - 0 1").abs() < 150);
+        assert_eq!(eval("8/8/8/3k4/8/8/8/1NN1K3 w - - 0 1"), 0);
+    }
+
+    #[test]
+    fn scale_factors() {
+        let scale = |fen, strong| {
+            let board = Board::from_fen(fen);
+            scale_factor(&board, strong, phase(&board))
+        };
+
+        // a dark squared bishop can't help the a-pawn promote on a8
+        assert_eq!(scale("k7/8/8/8/8/8/P7/K1B5 w - - 0 1", Color::White), 0);
+        assert!(scale("k7/8/8/8/8/8/P7/KB6 w - - 0 1", Color::White) > 0);
+        assert_eq!(scale("8/8/8/8/8/k7/p7/Kb6 b - - 0 1", Color::Black), 0);
+        // opposite colored bishops
+        assert_eq!(
+            scale("8/5k2/2b5/3p4/3P4/4B3/5K2/8 b - - 0 1", Color::White),
+            16
+        );
+        // pawnless and only a minor piece up
+        assert_eq!(
+            scale("8/8/3k4/8/8/3K4/3R4/3rB3 w - - 0 1", Color::White),
+            14
+        );
+        // fewer pawns, all on one wing
+        assert_eq!(scale("8/5pk1/8/8/8/8/5PPK/8 w - - 0 1", Color::White), 48);
+        assert_eq!(
+            scale(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                Color::White
+            ),
+            SCALE_NORMAL
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{board::zobrist::ZobristHash, eval::evaluation::EvalScore};
+
+#[derive(Debug, Copy, Clone)]
+struct EvalEntry {
+    key: u64,
+    eval: EvalScore,
+}
+
+// The same position is often evaluated more than once in a search (TT hits without a usable
+// eval, qsearch after razoring or ProbCut), so each search thread remembers its recent static
+// evals, keyed by the full zobrist hash.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    entries: Vec<Option<EvalEntry>>,
+}
+
+impl EvalCache {
+    const SIZE: usize = 1 << 14;
+
+    pub fn new() -> Self {
+        Self {
+            entries: vec![None; Self::SIZE],
+        }
+    }
+
+    const fn index(hash: ZobristHash) -> usize {
+        hash.as_usize() & (Self::SIZE - 1)
+    }
+
+    pub fn get(&self, hash: ZobristHash) -> Option<EvalScore> {
+        match self.entries[Self::index(hash)] {
+            Some(entry) if entry.key == hash.as_u64() => Some(entry.eval),
+            _ => None,
+        }
+    }
+
+    pub fn store(&mut self, hash: ZobristHash, eval: EvalScore) {
+        self.entries[Self::index(hash)] = Some(EvalEntry {
+            key: hash.as_u64(),
+            eval,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvalCache;
+    use crate::board::{board_representation::Board, zobrist::ZobristHash};
+
+    #[test]
+    fn only_hits_same_position() {
+        let white =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let black =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1");
+        let (white, black) = (ZobristHash::complete(&white), ZobristHash::complete(&black));
+
+        let mut cache = EvalCache::new();
+        assert_eq!(cache.get(white), None);
+
+        cache.store(white, 42);
+        assert_eq!(cache.get(white), Some(42));
+        assert_eq!(cache.get(black), None);
+
+        cache.store(white, -7);
+        assert_eq!(cache.get(white), Some(-7));
+    }
+}
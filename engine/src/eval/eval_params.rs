@@ -0,0 +1,382 @@
+use std::{
+    fmt, fs,
+    io::{self, ErrorKind},
+    sync::RwLock,
+};
+
+use crate::{
+    board::board_representation::{Color, Piece, Square, NUM_PIECES, NUM_RANKS, NUM_SQUARES},
+    eval::{
+        eval_constants::{
+            ATTACKS, BISHOP_FORWARD_MOBILITY, BISHOP_MOBILITY, BISHOP_PAIR_BONUS,
+            BISHOP_THREAT_ON_KNIGHT, BISHOP_THREAT_ON_QUEEN, BISHOP_THREAT_ON_ROOK, DEFENSES,
+            ENEMY_KING_RANK, FILE_STRUCTURE, ISOLATED_PAWNS_PRT, KNIGHT_FORWARD_MOBILITY,
+            KNIGHT_MOBILITY, KNIGHT_THREAT_ON_BISHOP, KNIGHT_THREAT_ON_QUEEN,
+            KNIGHT_THREAT_ON_ROOK, MATERIAL_PSTS, NON_STM_QUEEN_CONTACT_CHECKS,
+            PASSER_BLOCKERS_PRT, PASSER_PST, PASSER_SQ_RULE_BONUS, PAWN_STORM_BONUS,
+            PAWN_THREAT_ON_BISHOP, PAWN_THREAT_ON_KNIGHT, PAWN_THREAT_ON_QUEEN,
+            PAWN_THREAT_ON_ROOK, PHALANX_PAWNS_PRT, QUEEN_FORWARD_MOBILITY, QUEEN_MOBILITY,
+            ROOK_FORWARD_MOBILITY, ROOK_MOBILITY, ROOK_THREAT_ON_QUEEN, STM_QUEEN_CONTACT_CHECKS,
+            TEMPO_BONUS, TROPHISM_BONUS,
+        },
+        evaluation::{ScoreTuple, KINGSIDE_INDEX, QUEENSIDE_INDEX},
+        piece_loop_eval::MoveCounts,
+        piece_tables::{Prt, Pst},
+        trace::{
+            Attacks, BishopPair, Defenses, EnemyKingRank, FileStructure, ForwardMobility,
+            IsolatedPawns, MaterialPst, Mobility, NonStmQueenContactChecks, Passer, PasserBlocker,
+            PasserSqRule, PawnStorm, PhalanxPawns, StmQueenContactChecks, TempoBonus, Threats,
+            Tropism, LINEAR_TRACE_LEN, SAFETY_TRACE_LEN,
+        },
+    },
+};
+
+use Feature::{Linear, Safety};
+
+// the trace feature a weight belongs to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Feature {
+    Linear(usize),
+    Safety(usize),
+}
+
+// A table of weights, read and written flattened in the same order as in eval_constants.rs
+trait Table {
+    fn values(&self) -> Vec<ScoreTuple>;
+    // the values always have the length of the table
+    fn set_values(&mut self, values: &[ScoreTuple]);
+}
+
+impl Table for ScoreTuple {
+    fn values(&self) -> Vec<ScoreTuple> {
+        vec![*self]
+    }
+
+    fn set_values(&mut self, values: &[ScoreTuple]) {
+        *self = values[0];
+    }
+}
+
+impl Table for Pst {
+    fn values(&self) -> Vec<ScoreTuple> {
+        (0..NUM_SQUARES)
+            .map(|sq| self.access(Color::Black, Square::new(sq)))
+            .collect()
+    }
+
+    fn set_values(&mut self, values: &[ScoreTuple]) {
+        *self = Self::new(values.try_into().unwrap());
+    }
+}
+
+impl Table for Prt {
+    fn values(&self) -> Vec<ScoreTuple> {
+        (0..NUM_RANKS)
+            .map(|rank| self.access(Color::Black, Square::new(rank * 8)))
+            .collect()
+    }
+
+    fn set_values(&mut self, values: &[ScoreTuple]) {
+        *self = Self::new(values.try_into().unwrap());
+    }
+}
+
+impl<T: Table, const N: usize> Table for [T; N] {
+    fn values(&self) -> Vec<ScoreTuple> {
+        self.iter().flat_map(Table::values).collect()
+    }
+
+    fn set_values(&mut self, values: &[ScoreTuple]) {
+        let len = values.len() / N;
+        for (table, chunk) in self.iter_mut().zip(values.chunks_exact(len)) {
+            table.set_values(chunk);
+        }
+    }
+}
+
+const fn piece(i: usize) -> Piece {
+    Piece::LIST[i]
+}
+
+const fn square(i: usize) -> Square {
+    Square::new(i as u8)
+}
+
+macro_rules! eval_params {
+    ($($field:ident: $ty:ty = $constant:ident => $feature:expr),* $(,)?) => {
+        // Every weight of the hand crafted eval, named after the tables of eval_constants.rs
+        #[derive(Clone)]
+        pub struct EvalParams {
+            $(pub $field: $ty,)*
+        }
+
+        pub static DEFAULT_PARAMS: EvalParams = EvalParams {
+            $($field: $constant,)*
+        };
+
+        impl EvalParams {
+            // each table with the feature of every value in it
+            fn tables(&self) -> Vec<(&'static str, &dyn Table, fn(usize) -> Feature)> {
+                vec![$((stringify!($constant), &self.$field, $feature),)*]
+            }
+
+            fn tables_mut(&mut self) -> Vec<(&'static str, &mut dyn Table)> {
+                vec![$((stringify!($constant), &mut self.$field),)*]
+            }
+        }
+    };
+}
+
+eval_params! {
+    // the kingside tables share their features with the mirrored queenside ones
+    material_psts: [[Pst; NUM_PIECES as usize]; 2] = MATERIAL_PSTS
+        => |i| Linear(MaterialPst::index(i / 384, piece(i / 64 % 6), square(i % 64))),
+    passer_pst: Pst = PASSER_PST => |i| Linear(Passer::index(square(i))),
+    passer_blockers_prt: Prt = PASSER_BLOCKERS_PRT => |i| Linear(PasserBlocker::index(i as u8)),
+    isolated_pawns_prt: Prt = ISOLATED_PAWNS_PRT => |i| Linear(IsolatedPawns::index(i as u8)),
+    phalanx_pawns_prt: Prt = PHALANX_PAWNS_PRT => |i| Linear(PhalanxPawns::index(i as u8)),
+    bishop_pair_bonus: ScoreTuple = BISHOP_PAIR_BONUS => |_| Linear(BishopPair::index()),
+    knight_mobility: [ScoreTuple; MoveCounts::KNIGHT] = KNIGHT_MOBILITY
+        => |i| Linear(Mobility::index(Piece::KNIGHT, i)),
+    bishop_mobility: [ScoreTuple; MoveCounts::BISHOP] = BISHOP_MOBILITY
+        => |i| Linear(Mobility::index(Piece::BISHOP, i)),
+    rook_mobility: [ScoreTuple; MoveCounts::ROOK] = ROOK_MOBILITY
+        => |i| Linear(Mobility::index(Piece::ROOK, i)),
+    queen_mobility: [ScoreTuple; MoveCounts::QUEEN] = QUEEN_MOBILITY
+        => |i| Linear(Mobility::index(Piece::QUEEN, i)),
+    knight_forward_mobility: [ScoreTuple; MoveCounts::FORWARD_KNIGHT] = KNIGHT_FORWARD_MOBILITY
+        => |i| Linear(ForwardMobility::index(Piece::KNIGHT, i)),
+    bishop_forward_mobility: [ScoreTuple; MoveCounts::FORWARD_BISHOP] = BISHOP_FORWARD_MOBILITY
+        => |i| Linear(ForwardMobility::index(Piece::BISHOP, i)),
+    rook_forward_mobility: [ScoreTuple; MoveCounts::FORWARD_ROOK] = ROOK_FORWARD_MOBILITY
+        => |i| Linear(ForwardMobility::index(Piece::ROOK, i)),
+    queen_forward_mobility: [ScoreTuple; MoveCounts::FORWARD_QUEEN] = QUEEN_FORWARD_MOBILITY
+        => |i| Linear(ForwardMobility::index(Piece::QUEEN, i)),
+    pawn_threat_on_knight: [ScoreTuple; 2] = PAWN_THREAT_ON_KNIGHT
+        => |i| Linear(Threats::PAWN_THREAT_ON_KNIGHT + i),
+    pawn_threat_on_bishop: [ScoreTuple; 2] = PAWN_THREAT_ON_BISHOP
+        => |i| Linear(Threats::PAWN_THREAT_ON_BISHOP + i),
+    pawn_threat_on_rook: [ScoreTuple; 2] = PAWN_THREAT_ON_ROOK
+        => |i| Linear(Threats::PAWN_THREAT_ON_ROOK + i),
+    pawn_threat_on_queen: [ScoreTuple; 2] = PAWN_THREAT_ON_QUEEN
+        => |i| Linear(Threats::PAWN_THREAT_ON_QUEEN + i),
+    knight_threat_on_bishop: [ScoreTuple; 2] = KNIGHT_THREAT_ON_BISHOP
+        => |i| Linear(Threats::KNIGHT_THREAT_ON_BISHOP + i),
+    knight_threat_on_rook: [ScoreTuple; 2] = KNIGHT_THREAT_ON_ROOK
+        => |i| Linear(Threats::KNIGHT_THREAT_ON_ROOK + i),
+    knight_threat_on_queen: [ScoreTuple; 2] = KNIGHT_THREAT_ON_QUEEN
+        => |i| Linear(Threats::KNIGHT_THREAT_ON_QUEEN + i),
+    bishop_threat_on_knight: [ScoreTuple; 2] = BISHOP_THREAT_ON_KNIGHT
+        => |i| Linear(Threats::BISHOP_THREAT_ON_KNIGHT + i),
+    bishop_threat_on_rook: [ScoreTuple; 2] = BISHOP_THREAT_ON_ROOK
+        => |i| Linear(Threats::BISHOP_THREAT_ON_ROOK + i),
+    bishop_threat_on_queen: [ScoreTuple; 2] = BISHOP_THREAT_ON_QUEEN
+        => |i| Linear(Threats::BISHOP_THREAT_ON_QUEEN + i),
+    rook_threat_on_queen: [ScoreTuple; 2] = ROOK_THREAT_ON_QUEEN
+        => |i| Linear(Threats::ROOK_THREAT_ON_QUEEN + i),
+    passer_sq_rule_bonus: ScoreTuple = PASSER_SQ_RULE_BONUS => |_| Linear(PasserSqRule::index()),
+    tempo_bonus: ScoreTuple = TEMPO_BONUS => |_| Linear(TempoBonus::index()),
+    attacks: [[ScoreTuple; MoveCounts::QUEEN]; (NUM_PIECES - 1) as usize] = ATTACKS
+        => |i| Safety(Attacks::index(piece(i / MoveCounts::QUEEN), i % MoveCounts::QUEEN)),
+    defenses: [[ScoreTuple; MoveCounts::QUEEN]; (NUM_PIECES - 1) as usize] = DEFENSES
+        => |i| Safety(Defenses::index(piece(i / MoveCounts::QUEEN), i % MoveCounts::QUEEN)),
+    enemy_king_rank: Prt = ENEMY_KING_RANK => |i| Safety(EnemyKingRank::index(i as u8)),
+    trophism_bonus: [ScoreTuple; Tropism::LEN] = TROPHISM_BONUS => |i| Safety(Tropism::index(i)),
+    pawn_storm_bonus: [ScoreTuple; PawnStorm::LEN] = PAWN_STORM_BONUS
+        => |i| Safety(PawnStorm::index(i)),
+    file_structure: [ScoreTuple; FileStructure::LEN] = FILE_STRUCTURE
+        => |i| Safety(FileStructure::index(i)),
+    stm_queen_contact_checks: ScoreTuple = STM_QUEEN_CONTACT_CHECKS
+        => |_| Safety(StmQueenContactChecks::index()),
+    non_stm_queen_contact_checks: ScoreTuple = NON_STM_QUEEN_CONTACT_CHECKS
+        => |_| Safety(NonStmQueenContactChecks::index()),
+}
+
+// the params the eval uses, either the defaults or ones loaded from EvalParamsFile
+static LOADED: RwLock<Option<&'static EvalParams>> = RwLock::new(None);
+
+pub fn params() -> &'static EvalParams {
+    LOADED.read().unwrap().unwrap_or(&DEFAULT_PARAMS)
+}
+
+// evals in progress may still use the previous params, so they are leaked rather than freed
+pub fn set_params(params: Option<EvalParams>) {
+    *LOADED.write().unwrap() = params.map(|params| &*Box::leak(Box::new(params)));
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+impl EvalParams {
+    /// Reads lines of `NAME = mg eg, mg eg, ...`, where the names and the order of the values
+    /// are those of `eval_constants.rs`. Tables that are left out keep their default values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on unknown names, malformed values or tables of the wrong size, and on
+    /// kingside material PSTs that are not the mirrors of the queenside ones.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut params = DEFAULT_PARAMS.clone();
+        let mut tables = params.tables_mut();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, values) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected NAME = values, got \"{line}\"")))?;
+            let name = name.trim();
+            let (_, table) = tables
+                .iter_mut()
+                .find(|(table_name, _)| *table_name == name)
+                .ok_or_else(|| invalid(format!("unknown table {name}")))?;
+
+            let values = values
+                .split(',')
+                .map(
+                    |value| match value.split_whitespace().collect::<Vec<_>>()[..] {
+                        [mg, eg] => match (mg.parse(), eg.parse()) {
+                            (Ok(mg), Ok(eg)) => Ok(ScoreTuple::new(mg, eg)),
+                            _ => Err(invalid(format!(
+                                "{name}: \"{}\" is not a number",
+                                value.trim()
+                            ))),
+                        },
+                        _ => Err(invalid(format!(
+                            "{name}: expected \"mg eg\", got \"{}\"",
+                            value.trim()
+                        ))),
+                    },
+                )
+                .collect::<io::Result<Vec<_>>>()?;
+
+            let len = table.values().len();
+            if values.len() != len {
+                return Err(invalid(format!(
+                    "{name}: expected {len} values, got {}",
+                    values.len()
+                )));
+            }
+            table.set_values(&values);
+        }
+
+        drop(tables);
+        params.check_mirrored()?;
+        Ok(params)
+    }
+
+    // the eval only has features for the queenside PSTs, so the kingside ones can't differ
+    fn check_mirrored(&self) -> io::Result<()> {
+        let psts = &self.material_psts;
+        for (queenside, kingside) in psts[QUEENSIDE_INDEX].iter().zip(&psts[KINGSIDE_INDEX]) {
+            let (queenside, kingside) = (queenside.values(), kingside.values());
+            for sq in 0..NUM_SQUARES {
+                let mirrored = Square::new(sq).y_mirror().as_index();
+                if kingside[usize::from(sq)] != queenside[mirrored] {
+                    return Err(invalid(format!(
+                        "MATERIAL_PSTS: kingside square {sq} is not the mirror of the queenside"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // the weight of every trace feature
+    pub fn weights(&self) -> (Vec<ScoreTuple>, Vec<ScoreTuple>) {
+        let mut linear = vec![ScoreTuple::new(0, 0); LINEAR_TRACE_LEN];
+        let mut safety = vec![ScoreTuple::new(0, 0); SAFETY_TRACE_LEN];
+
+        for (_, table, feature) in self.tables() {
+            for (i, value) in table.values().into_iter().enumerate() {
+                match feature(i) {
+                    Linear(index) => linear[index] = value,
+                    Safety(index) => safety[index] = value,
+                }
+            }
+        }
+
+        (linear, safety)
+    }
+
+    // the params with the given weight for every trace feature, as the tuner finds them
+    pub fn from_weights(linear: &[ScoreTuple], safety: &[ScoreTuple]) -> Self {
+        let mut params = DEFAULT_PARAMS.clone();
+        let features = params
+            .tables()
+            .into_iter()
+            .map(|(_, _, feature)| feature)
+            .collect::<Vec<_>>();
+
+        for ((_, table), feature) in params.tables_mut().into_iter().zip(features) {
+            let values = (0..table.values().len())
+                .map(|i| match feature(i) {
+                    Linear(index) => linear[index],
+                    Safety(index) => safety[index],
+                })
+                .collect::<Vec<_>>();
+            table.set_values(&values);
+        }
+
+        params
+    }
+}
+
+// in the format read by parse()
+impl fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, table, _) in self.tables() {
+            let values = table
+                .values()
+                .iter()
+                .map(|value| format!("{} {}", value.mg(), value.eg()))
+                .collect::<Vec<_>>();
+            writeln!(f, "{name} = {}", values.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvalParams, DEFAULT_PARAMS};
+    use crate::eval::evaluation::ScoreTuple;
+
+    #[test]
+    fn round_trips_through_text() {
+        let text = DEFAULT_PARAMS.to_string();
+        assert_eq!(EvalParams::parse(&text).unwrap().to_string(), text);
+
+        let (linear, safety) = DEFAULT_PARAMS.weights();
+        assert_eq!(EvalParams::from_weights(&linear, &safety).to_string(), text);
+    }
+
+    #[test]
+    fn parses_partial_files() {
+        let params = EvalParams::parse("# tempo only\nTEMPO_BONUS = 10 -5\n").unwrap();
+        assert_eq!(params.tempo_bonus, ScoreTuple::new(10, -5));
+        assert_eq!(params.bishop_pair_bonus, DEFAULT_PARAMS.bishop_pair_bonus);
+
+        assert!(EvalParams::parse("TEMPO = 10 -5").is_err());
+        assert!(EvalParams::parse("TEMPO_BONUS = 10").is_err());
+        assert!(EvalParams::parse("TEMPO_BONUS = 10 5, 3 4").is_err());
+        assert!(EvalParams::parse("PASSER_PST = 1 2, 3 4").is_err());
+    }
+
+    #[test]
+    fn rejects_unmirrored_kingside_psts() {
+        let mut params = DEFAULT_PARAMS.clone();
+        params.material_psts[1][0] = params.material_psts[0][0].clone();
+        assert!(EvalParams::parse(&params.to_string()).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use std::fmt;
+
+use crate::{
+    board::board_representation::{Board, Color, NUM_COLORS},
+    eval::{
+        endgame::{self, scale_factor, Endgame, SCALE_NORMAL},
+        eval_params::params,
+        evaluation::{phase, trace_of_position, EvalScore, Phase, ScoreTuple, PHASE_MAX},
+        trace::{
+            Attacks, BishopPair, Defenses, EnemyKingRank, FileStructure, ForwardMobility,
+            IsolatedPawns, MaterialPst, Mobility, NonStmQueenContactChecks, Passer, PasserBlocker,
+            PasserSqRule, PawnStorm, PhalanxPawns, StmQueenContactChecks, TempoBonus, Threats,
+            Tropism,
+        },
+    },
+};
+
+const ZERO: ScoreTuple = ScoreTuple::new(0, 0);
+
+// (name, start, len) of each group of trace features shown as a row
+const LINEAR_TERMS: [(&str, usize, usize); 11] = [
+    ("Material/PST", MaterialPst::START, MaterialPst::LEN),
+    ("Passers", Passer::START, Passer::LEN),
+    ("Passer blockers", PasserBlocker::START, PasserBlocker::LEN),
+    ("Passer square", PasserSqRule::START, PasserSqRule::LEN),
+    ("Isolated pawns", IsolatedPawns::START, IsolatedPawns::LEN),
+    ("Phalanx pawns", PhalanxPawns::START, PhalanxPawns::LEN),
+    ("Bishop pair", BishopPair::START, BishopPair::LEN),
+    ("Mobility", Mobility::START, Mobility::LEN),
+    ("Fwd mobility", ForwardMobility::START, ForwardMobility::LEN),
+    ("Threats", Threats::START, Threats::LEN),
+    ("Tempo", TempoBonus::START, TempoBonus::LEN),
+];
+
+const SAFETY_TERMS: [(&str, usize, usize); 7] = [
+    ("Attacks", Attacks::START, Attacks::LEN),
+    ("Defenses", Defenses::START, Defenses::LEN),
+    ("Enemy king rank", EnemyKingRank::START, EnemyKingRank::LEN),
+    ("Tropism", Tropism::START, Tropism::LEN),
+    ("Pawn storm", PawnStorm::START, PawnStorm::LEN),
+    ("File structure", FileStructure::START, FileStructure::LEN),
+    (
+        "Queen contacts",
+        StmQueenContactChecks::START,
+        StmQueenContactChecks::LEN + NonStmQueenContactChecks::LEN,
+    ),
+];
+
+fn score_of(features: &[i8], weights: &[ScoreTuple]) -> ScoreTuple {
+    features
+        .iter()
+        .zip(weights)
+        .fold(ZERO, |acc, (&f, &w)| acc + w.mult(i32::from(f)))
+}
+
+fn term_scores<const N: usize>(
+    terms: &[(&'static str, usize, usize)],
+    trace: &[[i8; N]; NUM_COLORS as usize],
+    weights: &[ScoreTuple],
+) -> Vec<(&'static str, [ScoreTuple; NUM_COLORS as usize])> {
+    terms
+        .iter()
+        .map(|&(name, start, len)| {
+            let range = start..start + len;
+            let scores =
+                trace.map(|features| score_of(&features[range.clone()], &weights[range.clone()]));
+            (name, scores)
+        })
+        .collect()
+}
+
+// Every term of the hand crafted eval for both colors, rebuilt from the trace of the position
+pub struct EvalTable {
+    fen: String,
+    stm: Color,
+    linear: Vec<(&'static str, [ScoreTuple; NUM_COLORS as usize])>,
+    safety: Vec<(&'static str, [ScoreTuple; NUM_COLORS as usize])>,
+    attack_power: [ScoreTuple; NUM_COLORS as usize],
+    king_safety: [ScoreTuple; NUM_COLORS as usize],
+    total: [ScoreTuple; NUM_COLORS as usize],
+    phase: Phase,
+    // from white's point of view
+    tapered: EvalScore,
+    scale: i32,
+    endgame: Option<(Endgame, Color)>,
+    eval: EvalScore,
+}
+
+impl EvalTable {
+    pub fn new(board: &Board) -> Self {
+        let trace = trace_of_position(board);
+        let (linear_weights, safety_weights) = params().weights();
+
+        let linear = term_scores(&LINEAR_TERMS, &trace.linear, &linear_weights);
+        let safety = term_scores(&SAFETY_TERMS, &trace.safety, &safety_weights);
+
+        let attack_power =
+            Color::LIST.map(|color| score_of(&trace.safety[color.as_index()], &safety_weights));
+        let king_safety = attack_power.map(ScoreTuple::king_safety_formula);
+        let total = Color::LIST.map(|color| {
+            let i = color.as_index();
+            score_of(&trace.linear[i], &linear_weights) + king_safety[i]
+        });
+
+        let phase = phase(board);
+        let score = total[Color::White.as_index()] - total[Color::Black.as_index()];
+        let (mg_phase, eg_phase) = (i32::from(phase), i32::from(PHASE_MAX - phase));
+        let tapered = (score.mg() * mg_phase + score.eg() * eg_phase) / i32::from(PHASE_MAX);
+        let strong = if score.eg() >= 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let scale = scale_factor(board, strong, phase);
+        let scaled_eg = score.eg() * scale / SCALE_NORMAL;
+        let scaled = (score.mg() * mg_phase + scaled_eg * eg_phase) / i32::from(PHASE_MAX);
+
+        // specialized endgames replace the whole eval
+        let endgame = Endgame::probe(board);
+        let eval = endgame::evaluate(board).map_or(scaled, |eval| match board.color_to_move {
+            Color::White => eval,
+            Color::Black => -eval,
+        });
+
+        Self {
+            fen: board.to_fen(),
+            stm: board.color_to_move,
+            linear,
+            safety,
+            attack_power,
+            king_safety,
+            total,
+            phase,
+            tapered,
+            scale,
+            endgame,
+            eval,
+        }
+    }
+
+    // the final eval from the side to move's point of view, as evaluate() would give it
+    pub const fn eval(&self) -> EvalScore {
+        match self.stm {
+            Color::White => self.eval,
+            Color::Black => -self.eval,
+        }
+    }
+}
+
+const SEPARATOR: &str = "-----------------+--------------+--------------+-------------";
+
+fn write_row(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    [white, black]: [ScoreTuple; 2],
+    show_total: bool,
+) -> fmt::Result {
+    let cell = |s: ScoreTuple| format!("{:>6}{:>6}", s.mg(), s.eg());
+    let total = if show_total {
+        cell(white - black)
+    } else {
+        format!("{:>6}{:>6}", "-", "-")
+    };
+    writeln!(
+        f,
+        "{name:>16} | {} | {} | {total}",
+        cell(white),
+        cell(black)
+    )
+}
+
+impl fmt::Display for EvalTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Position: {}\n", self.fen)?;
+        writeln!(
+            f,
+            "{:>16} | {:^12} | {:^12} | {:>9}",
+            "Term", "White", "Black", "Total"
+        )?;
+        writeln!(
+            f,
+            "{:>16} | {:>6}{:>6} | {:>6}{:>6} | {:>6}{:>6}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG"
+        )?;
+        writeln!(f, "{SEPARATOR}")?;
+        for &(name, scores) in &self.linear {
+            write_row(f, name, scores, true)?;
+        }
+        writeln!(f, "{SEPARATOR}")?;
+        for &(name, scores) in &self.safety {
+            write_row(f, name, scores, false)?;
+        }
+        write_row(f, "Attack power", self.attack_power, false)?;
+        write_row(f, "King safety", self.king_safety, true)?;
+        writeln!(f, "{SEPARATOR}")?;
+        write_row(f, "Total", self.total, true)?;
+
+        writeln!(f, "\nPhase: {}/{PHASE_MAX}", self.phase)?;
+        writeln!(f, "Tapered eval: {} cp (white side)", self.tapered)?;
+        writeln!(
+            f,
+            "Scale factor: {}/{SCALE_NORMAL} (endgame part)",
+            self.scale
+        )?;
+        if let Some((endgame, strong)) = self.endgame {
+            writeln!(
+                f,
+                "Endgame: {} for {strong:?}, replaces the eval",
+                endgame.name()
+            )?;
+        }
+        write!(
+            f,
+            "Final eval: {} cp (white side), {} cp (side to move)",
+            self.eval,
+            self.eval()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvalTable;
+    use crate::{board::board_representation::Board, eval::evaluation::evaluate_hce};
+
+    #[test]
+    fn table_matches_eval() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "B2r2k1/3p1p2/p4PpB/1p3b2/8/2Nq2PP/PP2R1NK/3R4 b - - 2 23",
+            "r1b2rk1/pp1nqppp/2p1p3/3p4/2PPn3/2NBPN2/PPQ2PPP/R4RK1 b - - 1 10",
+            "8/2k5/3p4/p2P1p2/P2P1P2/8/3K4/8 w - - 0 1",
+            "8/5k2/2b5/3p4/3P4/4B3/5K2/8 b - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = Board::from_fen(fen);
+            assert_eq!(EvalTable::new(&board).eval(), evaluate_hce(&board), "{fen}");
+        }
+    }
+}
@@ -3,23 +3,22 @@ use std::ops::{Add, AddAssign, Sub};
 use crate::{
     bitloop,
     board::board_representation::{Bitboard, Board, Color, Piece, Square, NUM_COLORS, NUM_SQUARES},
-    eval::eval_constants::{
-        BISHOP_PAIR_BONUS, ISOLATED_PAWNS_PRT, MATERIAL_PSTS, PASSER_BLOCKERS_PRT, PASSER_PST,
-        PHALANX_PAWNS_PRT, TEMPO_BONUS,
-    },
+    eval::eval_params::params,
     eval::trace::{
         color_adjust, BishopPair, IsolatedPawns, MaterialPst, Passer, PasserBlocker, PhalanxPawns,
         TempoBonus, Trace,
     },
     eval::{
-        eval_constants::PASSER_SQ_RULE_BONUS, piece_loop_eval::mobility_threats_safety,
+        pawn_table::PawnTable,
+        piece_loop_eval::{mobility_threats_safety, pawn_safety},
         trace::PasserSqRule,
     },
     search::search::MAX_PLY,
     trace_update,
 };
 
-use super::drawishness::drawishness_adjustment;
+use super::endgame::{self, scale_factor, SCALE_NORMAL};
+use crate::board::attacks;
 
 const fn passer_squares_init(
     is_stm: bool,
@@ -79,11 +78,11 @@ impl ScoreTuple {
         Self(mg, eg)
     }
 
-    const fn mg(self) -> EvalScore {
+    pub const fn mg(self) -> EvalScore {
         self.0
     }
 
-    const fn eg(self) -> EvalScore {
+    pub const fn eg(self) -> EvalScore {
         self.1
     }
 
@@ -140,12 +139,13 @@ fn king_index(board: &Board, color: Color) -> usize {
 }
 
 fn pst_eval<const TRACE: bool>(board: &Board, color: Color, t: &mut Trace) -> ScoreTuple {
+    let params = params();
     let mut score = ScoreTuple::new(0, 0);
     let king_index = king_index(board, color);
 
     for piece in Piece::LIST {
         let mut pieces = board.piece_bb(piece, color);
-        let pst = &MATERIAL_PSTS[king_index][piece.as_index()];
+        let pst = &params.material_psts[king_index][piece.as_index()];
 
         bitloop!(|sq| pieces, {
             score += pst.access(color, sq);
@@ -166,19 +166,35 @@ fn bishop_pair<const TRACE: bool>(board: &Board, color: Color, t: &mut Trace) ->
             trace_update!(t, BishopPair, (), color, 1);
         }
 
-        BISHOP_PAIR_BONUS
+        params().bishop_pair_bonus
     } else {
         ScoreTuple::new(0, 0)
     }
 }
 
+fn passer_pst<const TRACE: bool>(mut passers: Bitboard, color: Color, t: &mut Trace) -> ScoreTuple {
+    let params = params();
+    let mut score = ScoreTuple::new(0, 0);
+    bitloop!(|sq| passers, {
+        score += params.passer_pst.access(color, sq);
+
+        if TRACE {
+            let sq = color_adjust(sq, color);
+            trace_update!(t, Passer, (sq), color, 1);
+        }
+    });
+    score
+}
+
+// the passer terms that depend on more than the pawns and kings
 fn passed_pawns<const IS_STM: bool, const TRACE: bool>(
     board: &Board,
     color: Color,
+    mut passers: Bitboard,
     t: &mut Trace,
 ) -> ScoreTuple {
+    let params = params();
     let mut score = ScoreTuple::new(0, 0);
-    let mut passers = board.passed_pawns(color);
 
     let mut blockers = match color {
         Color::White => passers
@@ -191,8 +207,6 @@ fn passed_pawns<const IS_STM: bool, const TRACE: bool>(
 
     let enemy_king = board.piece_bb(Piece::KING, color.flip());
     bitloop!(|sq| passers, {
-        score += PASSER_PST.access(color, sq);
-
         let passer_sq = if IS_STM {
             STM_PASSER_SQ[color.as_index()][sq.as_index()]
         } else {
@@ -200,21 +214,16 @@ fn passed_pawns<const IS_STM: bool, const TRACE: bool>(
         };
 
         if passer_sq.intersection(enemy_king).is_empty() {
-            score += PASSER_SQ_RULE_BONUS;
+            score += params.passer_sq_rule_bonus;
 
             if TRACE {
                 trace_update!(t, PasserSqRule, (), color, 1);
             }
         }
-
-        if TRACE {
-            let sq = color_adjust(sq, color);
-            trace_update!(t, Passer, (sq), color, 1);
-        }
     });
 
     bitloop!(|sq| blockers, {
-        score += PASSER_BLOCKERS_PRT.access(color, sq);
+        score += params.passer_blockers_prt.access(color, sq);
 
         if TRACE {
             let rank = color_adjust(sq, color).rank();
@@ -226,11 +235,12 @@ fn passed_pawns<const IS_STM: bool, const TRACE: bool>(
 }
 
 fn isolated_pawns<const TRACE: bool>(board: &Board, color: Color, t: &mut Trace) -> ScoreTuple {
+    let params = params();
     let mut score = ScoreTuple::new(0, 0);
 
     let mut isolated = board.isolated_pawns(color);
     bitloop!(|sq| isolated, {
-        score += ISOLATED_PAWNS_PRT.access(color, sq);
+        score += params.isolated_pawns_prt.access(color, sq);
 
         if TRACE {
             let rank = color_adjust(sq, color).rank();
@@ -242,11 +252,12 @@ fn isolated_pawns<const TRACE: bool>(board: &Board, color: Color, t: &mut Trace)
 }
 
 fn phalanx_pawns<const TRACE: bool>(board: &Board, color: Color, t: &mut Trace) -> ScoreTuple {
+    let params = params();
     let mut score = ScoreTuple::new(0, 0);
 
     let mut phalanx = board.phalanx_pawns(color);
     bitloop!(|sq| phalanx, {
-        score += PHALANX_PAWNS_PRT.access(color, sq);
+        score += params.phalanx_pawns_prt.access(color, sq);
 
         if TRACE {
             let rank = color_adjust(sq, color).rank();
@@ -257,39 +268,106 @@ fn phalanx_pawns<const TRACE: bool>(board: &Board, color: Color, t: &mut Trace)
     score
 }
 
-fn eval_or_trace<const TRACE: bool>(board: &Board, t: &mut Trace) -> EvalScore {
+// Terms that only depend on the pawns and kings, which is what the pawn hash table caches
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PawnEval {
+    pub passers: [Bitboard; NUM_COLORS as usize],
+    pub pawn_attacks: [Bitboard; NUM_COLORS as usize],
+    // from white's point of view
+    pub score: ScoreTuple,
+    // the pawn storm and file structure part of each color's attack on the enemy king
+    pub attack_power: [ScoreTuple; NUM_COLORS as usize],
+}
+
+pub fn pawn_eval<const TRACE: bool>(board: &Board, t: &mut Trace) -> PawnEval {
+    let mut passers = [Bitboard::EMPTY; NUM_COLORS as usize];
+    let mut pawn_attacks = [Bitboard::EMPTY; NUM_COLORS as usize];
+    let mut color_scores = [ScoreTuple::new(0, 0); NUM_COLORS as usize];
+    for color in Color::LIST {
+        let i = color.as_index();
+        passers[i] = board.passed_pawns(color);
+        pawn_attacks[i] = attacks::pawn_setwise(board.piece_bb(Piece::PAWN, color), color);
+        color_scores[i] = passer_pst::<TRACE>(passers[i], color, t)
+            + isolated_pawns::<TRACE>(board, color, t)
+            + phalanx_pawns::<TRACE>(board, color, t);
+    }
+
+    PawnEval {
+        passers,
+        pawn_attacks,
+        score: color_scores[Color::White.as_index()] - color_scores[Color::Black.as_index()],
+        attack_power: pawn_safety::<TRACE>(board, t),
+    }
+}
+
+fn eval_or_trace<const TRACE: bool>(board: &Board, pawns: &PawnEval, t: &mut Trace) -> EvalScore {
     let us = board.color_to_move;
     let them = board.color_to_move.flip();
 
+    if !TRACE {
+        if let Some(eval) = endgame::evaluate(board) {
+            return eval;
+        }
+    }
+
     if TRACE {
         let color = board.color_to_move;
         trace_update!(t, TempoBonus, (), color, 1);
     }
 
-    let mut score_tuple = TEMPO_BONUS;
+    let mut score_tuple = params().tempo_bonus;
     score_tuple += pst_eval::<TRACE>(board, us, t) - pst_eval::<TRACE>(board, them, t);
     score_tuple += bishop_pair::<TRACE>(board, us, t) - bishop_pair::<TRACE>(board, them, t);
-    score_tuple +=
-        passed_pawns::<true, TRACE>(board, us, t) - passed_pawns::<false, TRACE>(board, them, t);
-    score_tuple += isolated_pawns::<TRACE>(board, us, t) - isolated_pawns::<TRACE>(board, them, t);
-    score_tuple += phalanx_pawns::<TRACE>(board, us, t) - phalanx_pawns::<TRACE>(board, them, t);
-    score_tuple += mobility_threats_safety::<TRACE>(board, us, them, t);
+    score_tuple += if us == Color::White {
+        pawns.score
+    } else {
+        ScoreTuple::new(0, 0) - pawns.score
+    };
+    score_tuple += passed_pawns::<true, TRACE>(board, us, pawns.passers[us.as_index()], t)
+        - passed_pawns::<false, TRACE>(board, them, pawns.passers[them.as_index()], t);
+    score_tuple += mobility_threats_safety::<TRACE>(board, us, them, pawns, t);
 
     let phase = phase(board);
     let mg_phase = i32::from(phase);
     let eg_phase = i32::from(PHASE_MAX) - mg_phase;
 
-    let eval = (score_tuple.mg() * mg_phase + score_tuple.eg() * eg_phase) / i32::from(PHASE_MAX);
-    drawishness_adjustment(eval, board, phase)
+    let strong = if score_tuple.eg() >= 0 { us } else { them };
+    let eg = score_tuple.eg() * scale_factor(board, strong, phase) / SCALE_NORMAL;
+
+    (score_tuple.mg() * mg_phase + eg * eg_phase) / i32::from(PHASE_MAX)
 }
 
 pub fn evaluate(board: &Board) -> EvalScore {
-    eval_or_trace::<false>(board, &mut Trace::empty())
+    #[cfg(feature = "nnue")]
+    if let Some(eval) = board.accumulator.evaluate(board.color_to_move) {
+        return eval;
+    }
+
+    evaluate_hce(board)
+}
+
+// the hand crafted eval, even when there is a net
+pub fn evaluate_hce(board: &Board) -> EvalScore {
+    let mut t = Trace::empty();
+    let pawns = pawn_eval::<false>(board, &mut t);
+    eval_or_trace::<false>(board, &pawns, &mut t)
+}
+
+pub fn evaluate_cached(board: &Board, pawn_table: &mut PawnTable) -> EvalScore {
+    #[cfg(feature = "nnue")]
+    if let Some(eval) = board.accumulator.evaluate(board.color_to_move) {
+        return eval;
+    }
+
+    let pawns = pawn_table.probe(board);
+    eval_or_trace::<false>(board, &pawns, &mut Trace::empty())
 }
 
+// always computed from scratch, so that every term is traced
 pub fn trace_of_position(board: &Board) -> Trace {
     let mut trace = Trace::empty();
-    eval_or_trace::<true>(board, &mut trace);
+    let pawns = pawn_eval::<true>(board, &mut trace);
+    eval_or_trace::<true>(board, &pawns, &mut trace);
     trace
 }
 
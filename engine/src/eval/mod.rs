@@ -1,6 +1,13 @@
-mod drawishness;
+mod bitbase;
+pub mod endgame;
+pub mod eval_cache;
 mod eval_constants;
+pub mod eval_params;
+pub mod eval_table;
 pub mod evaluation;
+pub mod nnue;
+pub mod pawn_table;
 pub mod piece_loop_eval;
 mod piece_tables;
 pub mod trace;
+pub mod wdl;
@@ -0,0 +1,385 @@
+use std::{
+    fmt, fs,
+    io::{self, ErrorKind},
+    sync::{OnceLock, RwLock},
+};
+
+use crate::{
+    bitloop,
+    board::board_representation::{
+        Board, Color, Piece, Square, NUM_COLORS, NUM_PIECES, NUM_SQUARES,
+    },
+    eval::evaluation::{EvalScore, TB_LOSS_SCORE, TB_WIN_SCORE},
+};
+
+pub const NNUE_ENABLED: bool = cfg!(feature = "nnue");
+
+// (768 -> HIDDEN_SIZE)x2 -> 1, with a squared clipped ReLU on the accumulators
+pub const INPUT_SIZE: usize = (NUM_COLORS * NUM_PIECES) as usize * NUM_SQUARES as usize;
+pub const HIDDEN_SIZE: usize = 256;
+
+// quantization of the accumulator and output weights, and the eval scale the net was trained at
+pub const QA: i32 = 255;
+pub const QB: i32 = 64;
+pub const SCALE: i32 = 400;
+
+// Bounds on the trained weights that keep every sum in range: the bias and up to 32 feature
+// weights in an i16 accumulator, and 2 * HIDDEN_SIZE products of x * x * w in an i32.
+pub const MAX_FEATURE_WEIGHT: f32 = 1.98;
+#[allow(clippy::cast_possible_wrap)]
+pub const MAX_OUTPUT_WEIGHT: i32 = i32::MAX / (2 * HIDDEN_SIZE as i32 * QA * QA);
+
+// little endian i16s: feature weights, feature biases, output weights, output bias,
+// zero padded up to a multiple of 64 bytes
+const NET_VALUES: usize = INPUT_SIZE * HIDDEN_SIZE + HIDDEN_SIZE + 2 * HIDDEN_SIZE + 1;
+const NET_BYTES: usize = NET_VALUES * 2;
+const NET_PADDING: usize = 64;
+
+// set at build time from the EVALFILE environment variable, empty if there isn't one
+static EMBEDDED_NET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/net.bin"));
+
+static EMBEDDED: OnceLock<Option<&'static Network>> = OnceLock::new();
+// the net loaded from EvalFile, which replaces the embedded one
+static LOADED: RwLock<Option<&'static Network>> = RwLock::new(None);
+
+pub struct Network {
+    feature_weights: Vec<[i16; HIDDEN_SIZE]>,
+    feature_bias: [i16; HIDDEN_SIZE],
+    // the side to move's half first
+    output_weights: [[i16; HIDDEN_SIZE]; 2],
+    output_bias: i16,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Network")
+    }
+}
+
+impl Network {
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a net of the current architecture.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < NET_BYTES || bytes.len() >= NET_BYTES + NET_PADDING {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("expected a {INPUT_SIZE}->{HIDDEN_SIZE}x2->1 net of {NET_BYTES} bytes"),
+            ));
+        }
+
+        let mut values = bytes[..NET_BYTES]
+            .chunks_exact(2)
+            .map(|x| i16::from_le_bytes([x[0], x[1]]));
+        let mut row = || {
+            let mut row = [0; HIDDEN_SIZE];
+            for (x, value) in row.iter_mut().zip(&mut values) {
+                *x = value;
+            }
+            row
+        };
+
+        let feature_weights = (0..INPUT_SIZE).map(|_| row()).collect();
+        let feature_bias = row();
+        let output_weights = [row(), row()];
+        let output_bias = values.next().unwrap();
+
+        Ok(Self {
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a net of the current architecture.
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    fn evaluate(&self, us: &[i16; HIDDEN_SIZE], them: &[i16; HIDDEN_SIZE]) -> EvalScore {
+        let mut output = 0;
+        for (acc, weights) in [us, them].into_iter().zip(&self.output_weights) {
+            for (&x, &w) in acc.iter().zip(weights) {
+                let x = i32::from(x).clamp(0, QA);
+                output += x * x * i32::from(w);
+            }
+        }
+
+        let output = i64::from(output / QA + i32::from(self.output_bias));
+        let eval = output * i64::from(SCALE) / i64::from(QA * QB);
+        eval.clamp(i64::from(TB_LOSS_SCORE + 1), i64::from(TB_WIN_SCORE - 1)) as EvalScore
+    }
+}
+
+// the net used by new boards: the one from EvalFile if set, otherwise the embedded one
+pub fn active_network() -> Option<&'static Network> {
+    LOADED.read().unwrap().or_else(|| {
+        *EMBEDDED.get_or_init(|| {
+            if EMBEDDED_NET.is_empty() {
+                None
+            } else {
+                let net = Network::from_bytes(EMBEDDED_NET).expect("embedded net is invalid");
+                Some(Box::leak(Box::new(net)))
+            }
+        })
+    })
+}
+
+// boards may still hold the previous net, so it is leaked rather than freed
+pub fn set_loaded_network(net: Option<Network>) {
+    *LOADED.write().unwrap() = net.map(|net| &*Box::leak(Box::new(net)));
+}
+
+pub const fn feature_index(perspective: Color, color: Color, piece: Piece, sq: Square) -> usize {
+    // each side sees the board from its own point of view, with its own pieces first
+    let (side, sq) = match perspective {
+        Color::White => (color.as_index(), sq),
+        Color::Black => (color.flip().as_index(), sq.mirror()),
+    };
+
+    (side * NUM_PIECES as usize + piece.as_index()) * NUM_SQUARES as usize + sq.as_index()
+}
+
+#[derive(Clone, PartialEq, Eq)]
+#[repr(align(64))]
+struct Half([i16; HIDDEN_SIZE]);
+
+// Hidden layer of both perspectives before activation, kept up to date by the board as moves
+// are played. Inactive, and all zeros, when there is no net.
+#[derive(Clone)]
+pub struct Accumulator {
+    net: Option<&'static Network>,
+    halves: [Half; NUM_COLORS as usize],
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            net: None,
+            halves: [Half([0; HIDDEN_SIZE]), Half([0; HIDDEN_SIZE])],
+        }
+    }
+}
+
+impl fmt::Debug for Accumulator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Accumulator")
+            .field("active", &self.net.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+// boards are equal regardless of which net they were set up with
+impl PartialEq for Accumulator {
+    fn eq(&self, other: &Self) -> bool {
+        self.halves == other.halves
+    }
+}
+
+impl Eq for Accumulator {}
+
+impl Accumulator {
+    pub fn new(board: &Board, net: Option<&'static Network>) -> Self {
+        let mut acc = Self {
+            net,
+            ..Self::default()
+        };
+        let Some(net) = net else {
+            return acc;
+        };
+
+        for half in &mut acc.halves {
+            half.0 = net.feature_bias;
+        }
+        for color in Color::LIST {
+            for piece in Piece::LIST {
+                let mut piece_bb = board.piece_bb(piece, color);
+                bitloop!(|sq| piece_bb, {
+                    acc.add(color, piece, sq);
+                });
+            }
+        }
+
+        acc
+    }
+
+    pub const fn is_active(&self) -> bool {
+        self.net.is_some()
+    }
+
+    pub const fn network(&self) -> Option<&'static Network> {
+        self.net
+    }
+
+    pub fn add(&mut self, color: Color, piece: Piece, sq: Square) {
+        let Some(net) = self.net else { return };
+        for perspective in Color::LIST {
+            let weights = &net.feature_weights[feature_index(perspective, color, piece, sq)];
+            for (x, &w) in self.halves[perspective.as_index()]
+                .0
+                .iter_mut()
+                .zip(weights)
+            {
+                *x += w;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, color: Color, piece: Piece, sq: Square) {
+        let Some(net) = self.net else { return };
+        for perspective in Color::LIST {
+            let weights = &net.feature_weights[feature_index(perspective, color, piece, sq)];
+            for (x, &w) in self.halves[perspective.as_index()]
+                .0
+                .iter_mut()
+                .zip(weights)
+            {
+                *x -= w;
+            }
+        }
+    }
+
+    // a remove and an add in a single pass
+    pub fn move_piece(&mut self, color: Color, from: (Piece, Square), to: (Piece, Square)) {
+        let Some(net) = self.net else { return };
+        for perspective in Color::LIST {
+            let sub = &net.feature_weights[feature_index(perspective, color, from.0, from.1)];
+            let add = &net.feature_weights[feature_index(perspective, color, to.0, to.1)];
+            let half = &mut self.halves[perspective.as_index()].0;
+            for ((x, &s), &a) in half.iter_mut().zip(sub).zip(add) {
+                *x += a - s;
+            }
+        }
+    }
+
+    // None if there is no net, so the hand crafted eval is used instead
+    pub fn evaluate(&self, stm: Color) -> Option<EvalScore> {
+        let net = self.net?;
+        let us = &self.halves[stm.as_index()].0;
+        let them = &self.halves[stm.flip().as_index()].0;
+        Some(net.evaluate(us, them))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Network, HIDDEN_SIZE, INPUT_SIZE, MAX_FEATURE_WEIGHT, MAX_OUTPUT_WEIGHT, NET_BYTES, QA,
+    };
+
+    #[cfg(feature = "nnue")]
+    fn random_net() -> &'static Network {
+        let mut seed: u32 = 0x9e37_79b9;
+        let bytes: Vec<u8> = (0..NET_BYTES / 2)
+            .flat_map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                // small weights, so that sums of them can't overflow
+                (((seed % 256) as i16) - 128).to_le_bytes()
+            })
+            .collect();
+        Box::leak(Box::new(Network::from_bytes(&bytes).unwrap()))
+    }
+
+    // every weight at the bound the trainer clips to, saturating the hidden layer
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    fn saturated_net(sign: i16) -> &'static Network {
+        let feature = (MAX_FEATURE_WEIGHT * QA as f32) as i16;
+        let output = sign * MAX_OUTPUT_WEIGHT as i16;
+        let values = (0..(INPUT_SIZE + 1) * HIDDEN_SIZE)
+            .map(|_| feature)
+            .chain((0..2 * HIDDEN_SIZE).map(|_| output))
+            .chain([sign * i16::MAX]);
+        let bytes: Vec<u8> = values.flat_map(i16::to_le_bytes).collect();
+        Box::leak(Box::new(Network::from_bytes(&bytes).unwrap()))
+    }
+
+    #[test]
+    fn saturated_weights_dont_overflow() {
+        use super::Accumulator;
+        use crate::{
+            board::board_representation::{Board, Color, Piece, Square, START_FEN},
+            eval::evaluation::{TB_LOSS_SCORE, TB_WIN_SCORE},
+        };
+
+        let board = Board::from_fen(START_FEN);
+        for (sign, bound) in [(1, TB_WIN_SCORE - 1), (-1, TB_LOSS_SCORE + 1)] {
+            let net = saturated_net(sign);
+            let mut acc = Accumulator::new(&board, Some(net));
+            assert_eq!(acc.evaluate(Color::White), Some(bound));
+
+            // the incremental updates stay in range too
+            acc.move_piece(
+                Color::White,
+                (Piece::PAWN, Square::new(12)),
+                (Piece::QUEEN, Square::new(60)),
+            );
+            acc.remove(Color::Black, Piece::QUEEN, Square::new(59));
+            acc.add(Color::Black, Piece::QUEEN, Square::new(59));
+            assert_eq!(acc.evaluate(Color::Black), Some(bound));
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_size() {
+        assert!(Network::from_bytes(&[0; 64]).is_err());
+        assert!(Network::from_bytes(&vec![0; NET_BYTES + 64]).is_err());
+        assert!(Network::from_bytes(&vec![0; NET_BYTES + 32]).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "nnue")]
+    fn incremental_matches_refresh() {
+        use super::Accumulator;
+        use crate::board::{
+            board_representation::Board, chess_move::Move, zobrist::ZobristHash,
+            zobrist_stack::ZobristStack,
+        };
+
+        let net = random_net();
+        let games: [(&str, &[&str]); 3] = [
+            // castling both ways, captures and a capture promotion
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                &["e1g1", "e8c8", "e5f7", "h3g2", "f7d8", "g2f1q"],
+            ),
+            // underpromotions
+            (
+                "8/P5k1/8/8/8/8/7p/K7 w - - 0 1",
+                &["a7a8n", "h2h1r", "a1b2", "g7f7"],
+            ),
+            // en passant
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                &[
+                    "e2e4", "d7d5", "e4e5", "f7f5", "e5f6", "g8f6", "b1c3", "d5d4",
+                ],
+            ),
+        ];
+
+        for (fen, moves) in games {
+            let mut board = Board::from_fen(fen);
+            board.set_network(Some(net));
+            let mut zobrist_stack = ZobristStack::new(&board);
+            for mv in moves {
+                let mv = Move::from_string(mv, &board);
+                let hash_base = ZobristHash::incremental_update_base(&board);
+                assert!(
+                    board.try_play_move(mv, &mut zobrist_stack, hash_base),
+                    "{fen} {}",
+                    mv.as_string()
+                );
+                let fresh = Accumulator::new(&board, Some(net));
+                assert!(board.accumulator == fresh, "{fen} {}", mv.as_string());
+                assert_eq!(
+                    board.accumulator.evaluate(board.color_to_move),
+                    fresh.evaluate(board.color_to_move)
+                );
+            }
+        }
+    }
+}
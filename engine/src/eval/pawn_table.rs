@@ -0,0 +1,76 @@
+use crate::{
+    board::board_representation::Board,
+    eval::{
+        evaluation::{pawn_eval, PawnEval},
+        trace::Trace,
+    },
+};
+
+#[derive(Debug, Copy, Clone)]
+struct PawnEntry {
+    key: u64,
+    eval: PawnEval,
+}
+
+// Pawn structure rarely changes between nodes, so each search thread keeps its own cache of
+// the terms that only depend on the pawns and kings, keyed by the board's pawn hash.
+#[derive(Debug, Clone)]
+pub struct PawnTable {
+    entries: Vec<Option<PawnEntry>>,
+}
+
+impl PawnTable {
+    const SIZE: usize = 1 << 13;
+
+    pub fn new() -> Self {
+        Self {
+            entries: vec![None; Self::SIZE],
+        }
+    }
+
+    pub fn probe(&mut self, board: &Board) -> PawnEval {
+        let key = board.pawn_hash.as_u64();
+        let slot = &mut self.entries[board.pawn_hash.as_usize() & (Self::SIZE - 1)];
+
+        match slot {
+            Some(entry) if entry.key == key => entry.eval,
+            _ => {
+                let eval = pawn_eval::<false>(board, &mut Trace::empty());
+                *slot = Some(PawnEntry { key, eval });
+                eval
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PawnTable;
+    use crate::{
+        board::board_representation::Board,
+        eval::{evaluation::evaluate, evaluation::evaluate_cached},
+    };
+
+    #[test]
+    fn cached_eval_matches() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            // same pawns and kings as above with the other side to move
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+
+        let mut table = PawnTable::new();
+        for _ in 0..2 {
+            for fen in fens {
+                let board = Board::from_fen(fen);
+                assert_eq!(
+                    evaluate_cached(&board, &mut table),
+                    evaluate(&board),
+                    "{fen}"
+                );
+            }
+        }
+    }
+}
@@ -4,27 +4,20 @@ use crate::{
     board::board_representation::{
         Bitboard, Board, Color, Piece, Square, NUM_COLORS, NUM_FILES, NUM_SQUARES,
     },
-    eval::eval_constants::{
-        ATTACKS, BISHOP_FORWARD_MOBILITY, BISHOP_MOBILITY, BISHOP_THREAT_ON_KNIGHT,
-        BISHOP_THREAT_ON_QUEEN, BISHOP_THREAT_ON_ROOK, DEFENSES, ENEMY_KING_RANK,
-        KNIGHT_FORWARD_MOBILITY, KNIGHT_MOBILITY, KNIGHT_THREAT_ON_BISHOP, KNIGHT_THREAT_ON_QUEEN,
-        KNIGHT_THREAT_ON_ROOK, PAWN_STORM_BONUS, PAWN_THREAT_ON_BISHOP, PAWN_THREAT_ON_KNIGHT,
-        PAWN_THREAT_ON_QUEEN, PAWN_THREAT_ON_ROOK, QUEEN_FORWARD_MOBILITY, QUEEN_MOBILITY,
-        ROOK_FORWARD_MOBILITY, ROOK_MOBILITY, ROOK_THREAT_ON_QUEEN, TROPHISM_BONUS,
-    },
     eval::{
-        eval_constants::FILE_STRUCTURE,
+        eval_params::{params, EvalParams},
         trace::{
             color_adjust, Attacks, Defenses, EnemyKingRank, FileStructure, ForwardMobility,
             Mobility, NonStmQueenContactChecks, PawnStorm, StmQueenContactChecks, Threats, Trace,
         },
     },
-    eval::{evaluation::ScoreTuple, trace::Tropism},
+    eval::{
+        evaluation::{PawnEval, ScoreTuple},
+        trace::Tropism,
+    },
     trace_safety_update, trace_threat_update, trace_update,
 };
 
-use super::eval_constants::{NON_STM_QUEEN_CONTACT_CHECKS, STM_QUEEN_CONTACT_CHECKS};
-
 const STM_US: usize = 0;
 const STM_THEM: usize = 1;
 
@@ -123,10 +116,8 @@ const fn tropism(king_sq: Square, piece_sq: Square) -> usize {
     TROPISM[king_sq.as_index()][piece_sq.as_index()]
 }
 
-const fn availible(board: &Board, color: Color) -> Bitboard {
+const fn availible(board: &Board, color: Color, enemy_pawn_attacks: Bitboard) -> Bitboard {
     let opp_color = color.flip();
-    let enemy_pawns = board.piece_bb(Piece::PAWN, opp_color);
-    let enemy_pawn_attacks = attacks::pawn_setwise(enemy_pawns, opp_color);
     let enemy_or_empty = board.all[opp_color.as_index()].union(board.empty());
 
     enemy_or_empty.without(enemy_pawn_attacks)
@@ -176,6 +167,7 @@ impl ConstPiece {
 }
 
 struct LoopEvaluator {
+    params: &'static EvalParams,
     stm: usize,
     color: Color,
     availible: Bitboard,
@@ -199,10 +191,11 @@ impl LoopEvaluator {
         board: &Board,
         own_virt_mob: usize,
         enemy_virt_mob: usize,
+        enemy_pawn_attacks: Bitboard,
         color: Color,
         stm: usize,
     ) -> Self {
-        let availible = availible(board, color);
+        let availible = availible(board, color, enemy_pawn_attacks);
         let enemy_king_sq = board.color_king_sq(color.flip());
         let friendly_king_zone = king_zone(board, color);
         let enemy_king_zone = king_zone(board, color.flip());
@@ -220,6 +213,7 @@ impl LoopEvaluator {
         let hv_occupied = occ ^ hv_sliders;
         let d12_occupied = occ ^ d12_sliders;
         Self {
+            params: params(),
             stm,
             color,
             availible,
@@ -269,8 +263,8 @@ impl LoopEvaluator {
 
         let kz_attacks = self.enemy_king_zone.intersection(moves).popcount() as i32;
         let kz_defenses = self.friendly_king_zone.intersection(moves).popcount() as i32;
-        attack_power[color.as_index()] += ATTACKS[piece.as_index()][self.enemy_virt_mob].mult(kz_attacks);
-        attack_power[opp_color.as_index()] += DEFENSES[piece.as_index()][self.own_virt_mob].mult(kz_defenses);
+        attack_power[color.as_index()] += self.params.attacks[piece.as_index()][self.enemy_virt_mob].mult(kz_attacks);
+        attack_power[opp_color.as_index()] += self.params.defenses[piece.as_index()][self.own_virt_mob].mult(kz_defenses);
 
         self.tropism += tropism(self.enemy_king_sq, sq);
 
@@ -278,13 +272,13 @@ impl LoopEvaluator {
             ConstPiece::KNIGHT => {
                 attack_info[color.as_index()].non_queen |= attacks;
 
-                score += KNIGHT_MOBILITY[mobility];
-                score += KNIGHT_FORWARD_MOBILITY[forward_mobility];
+                score += self.params.knight_mobility[mobility];
+                score += self.params.knight_forward_mobility[forward_mobility];
 
-                score += KNIGHT_THREAT_ON_BISHOP[self.stm]
+                score += self.params.knight_threat_on_bishop[self.stm]
                     .mult((attacks & self.enemy_bishops).popcount() as i32)
-                    + KNIGHT_THREAT_ON_ROOK[self.stm].mult((attacks & self.enemy_rooks).popcount() as i32)
-                    + KNIGHT_THREAT_ON_QUEEN[self.stm].mult((attacks & self.enemy_queens).popcount() as i32);
+                    + self.params.knight_threat_on_rook[self.stm].mult((attacks & self.enemy_rooks).popcount() as i32)
+                    + self.params.knight_threat_on_queen[self.stm].mult((attacks & self.enemy_queens).popcount() as i32);
 
                 if TRACE {
                     trace_threat_update!(t, KNIGHT_THREAT_ON_BISHOP, color, attacks, self.enemy_bishops, self.stm);
@@ -295,13 +289,13 @@ impl LoopEvaluator {
             ConstPiece::BISHOP => {
                 attack_info[color.as_index()].non_queen |= attacks;
 
-                score += BISHOP_MOBILITY[mobility];
-                score += BISHOP_FORWARD_MOBILITY[forward_mobility];
+                score += self.params.bishop_mobility[mobility];
+                score += self.params.bishop_forward_mobility[forward_mobility];
 
-                score += BISHOP_THREAT_ON_KNIGHT[self.stm]
+                score += self.params.bishop_threat_on_knight[self.stm]
                     .mult((attacks & self.enemy_knights).popcount() as i32)
-                    + BISHOP_THREAT_ON_ROOK[self.stm].mult((attacks & self.enemy_rooks).popcount() as i32)
-                    + BISHOP_THREAT_ON_QUEEN[self.stm].mult((attacks & self.enemy_queens).popcount() as i32);
+                    + self.params.bishop_threat_on_rook[self.stm].mult((attacks & self.enemy_rooks).popcount() as i32)
+                    + self.params.bishop_threat_on_queen[self.stm].mult((attacks & self.enemy_queens).popcount() as i32);
 
                     if TRACE {
                         trace_threat_update!(t, BISHOP_THREAT_ON_KNIGHT, color, attacks, self.enemy_knights, self.stm);
@@ -312,10 +306,10 @@ impl LoopEvaluator {
             ConstPiece::ROOK => {
                 attack_info[color.as_index()].non_queen |= attacks;
 
-                score += ROOK_MOBILITY[mobility];
-                score += ROOK_FORWARD_MOBILITY[forward_mobility];
+                score += self.params.rook_mobility[mobility];
+                score += self.params.rook_forward_mobility[forward_mobility];
 
-                score += ROOK_THREAT_ON_QUEEN[self.stm].mult((attacks & self.enemy_queens).popcount() as i32);
+                score += self.params.rook_threat_on_queen[self.stm].mult((attacks & self.enemy_queens).popcount() as i32);
 
                 if TRACE {
                     trace_threat_update!(t, ROOK_THREAT_ON_QUEEN, color, attacks, self.enemy_queens, self.stm);
@@ -324,8 +318,8 @@ impl LoopEvaluator {
             ConstPiece::QUEEN => {
                 attack_info[color.as_index()].queen |= attacks;
 
-                score += QUEEN_MOBILITY[mobility];
-                score += QUEEN_FORWARD_MOBILITY[forward_mobility];
+                score += self.params.queen_mobility[mobility];
+                score += self.params.queen_forward_mobility[forward_mobility];
             }
             _ => (),
         }
@@ -350,18 +344,17 @@ impl LoopEvaluator {
     #[allow(clippy::cast_possible_wrap)]
     #[rustfmt::skip]
     fn pawn_score<const TRACE: bool>(
-        &self, pawns: Bitboard,
+        &self, pawn_attacks: Bitboard,
         color: Color,
         attack_power: &mut [ScoreTuple; 2],
         attack_info: &mut [AttackInfo; 2],
         t: &mut Trace
     ) -> ScoreTuple {
         let piece = Piece::PAWN;
-        let pawn_attacks = attacks::pawn_setwise(pawns, color);
         let kz_attacks = self.enemy_king_zone.intersection(pawn_attacks).popcount() as i32;
         let kz_defenses = self.friendly_king_zone.intersection(pawn_attacks).popcount() as i32;
-        attack_power[color.as_index()] += ATTACKS[piece.as_index()][self.enemy_virt_mob].mult(kz_attacks);
-        attack_power[color.flip().as_index()] += DEFENSES[piece.as_index()][self.own_virt_mob].mult(kz_defenses);
+        attack_power[color.as_index()] += self.params.attacks[piece.as_index()][self.enemy_virt_mob].mult(kz_attacks);
+        attack_power[color.flip().as_index()] += self.params.defenses[piece.as_index()][self.own_virt_mob].mult(kz_defenses);
 
         attack_info[color.as_index()].non_queen |= pawn_attacks;
 
@@ -376,10 +369,10 @@ impl LoopEvaluator {
             trace_threat_update!(t, PAWN_THREAT_ON_QUEEN, self.color, pawn_attacks, self.enemy_queens, self.stm);
         }
 
-        PAWN_THREAT_ON_KNIGHT[self.stm].mult((pawn_attacks & self.enemy_knights).popcount() as i32)
-            + PAWN_THREAT_ON_BISHOP[self.stm].mult((pawn_attacks & self.enemy_bishops).popcount() as i32)
-            + PAWN_THREAT_ON_ROOK[self.stm].mult((pawn_attacks & self.enemy_rooks).popcount() as i32)
-            + PAWN_THREAT_ON_QUEEN[self.stm].mult((pawn_attacks & self.enemy_queens).popcount() as i32)
+        self.params.pawn_threat_on_knight[self.stm].mult((pawn_attacks & self.enemy_knights).popcount() as i32)
+            + self.params.pawn_threat_on_bishop[self.stm].mult((pawn_attacks & self.enemy_bishops).popcount() as i32)
+            + self.params.pawn_threat_on_rook[self.stm].mult((pawn_attacks & self.enemy_rooks).popcount() as i32)
+            + self.params.pawn_threat_on_queen[self.stm].mult((pawn_attacks & self.enemy_queens).popcount() as i32)
     }
 
     fn piece_loop<const PIECE: u8, const TRACE: bool>(
@@ -458,7 +451,7 @@ fn safety_file_stucture<const TRACE: bool>(
             + 4_u32.pow(3) * locked_files.intersection(attacking_zone).popcount())
             as usize;
 
-        attack_power[color.as_index()] += FILE_STRUCTURE[index];
+        attack_power[color.as_index()] += params().file_structure[index];
 
         if TRACE {
             trace_safety_update!(t, FileStructure, (index), color, 1);
@@ -497,9 +490,28 @@ fn safe_queen_contact_checks(board: &Board, attack_info: &[AttackInfo; 2], color
     count as i32
 }
 
+// the king safety terms that only depend on the pawns and kings
+pub fn pawn_safety<const TRACE: bool>(board: &Board, t: &mut Trace) -> [ScoreTuple; 2] {
+    let mut attack_power = [ScoreTuple::new(0, 0), ScoreTuple::new(0, 0)];
+
+    for color in Color::LIST {
+        let pawns = board.piece_bb(Piece::PAWN, color);
+        let pawn_trop = pawn_storm_tropism(board.color_king_sq(color.flip()), pawns);
+        attack_power[color.as_index()] += params().pawn_storm_bonus[pawn_trop];
+
+        if TRACE {
+            trace_safety_update!(t, PawnStorm, (pawn_trop), color, 1);
+        }
+    }
+
+    safety_file_stucture::<TRACE>(board, &mut attack_power, t);
+    attack_power
+}
+
 #[allow(clippy::too_many_arguments)]
 fn one_sided_eval<const TRACE: bool>(
     board: &Board,
+    pawns: &PawnEval,
     attack_power: &mut [ScoreTuple; 2],
     attack_info: &mut [AttackInfo; 2],
     own_virt_mob: usize,
@@ -512,9 +524,17 @@ fn one_sided_eval<const TRACE: bool>(
     let bishops = board.piece_bb(Piece::BISHOP, color);
     let rooks = board.piece_bb(Piece::ROOK, color);
     let queens = board.piece_bb(Piece::QUEEN, color);
-    let pawns = board.piece_bb(Piece::PAWN, color);
+    let pawn_attacks = pawns.pawn_attacks[color.as_index()];
+    let enemy_pawn_attacks = pawns.pawn_attacks[color.flip().as_index()];
 
-    let mut looper = LoopEvaluator::new(board, own_virt_mob, enemy_virt_mob, color, stm);
+    let mut looper = LoopEvaluator::new(
+        board,
+        own_virt_mob,
+        enemy_virt_mob,
+        enemy_pawn_attacks,
+        color,
+        stm,
+    );
     let score =
         looper.piece_loop::<{ ConstPiece::KNIGHT }, TRACE>(knights, attack_power, attack_info, t)
             + looper.piece_loop::<{ ConstPiece::BISHOP }, TRACE>(
@@ -530,23 +550,19 @@ fn one_sided_eval<const TRACE: bool>(
                 attack_info,
                 t,
             )
-            + looper.pawn_score::<TRACE>(pawns, color, attack_power, attack_info, t);
+            + looper.pawn_score::<TRACE>(pawn_attacks, color, attack_power, attack_info, t);
 
     let opp_king_sq = board.color_king_sq(color.flip());
-    attack_power[color.as_index()] += ENEMY_KING_RANK.access(color, opp_king_sq);
+    attack_power[color.as_index()] += looper.params.enemy_king_rank.access(color, opp_king_sq);
 
     let trop = looper.tropism;
-    attack_power[color.as_index()] += TROPHISM_BONUS[trop];
-
-    let pawn_trop = pawn_storm_tropism(looper.enemy_king_sq, pawns);
-    attack_power[color.as_index()] += PAWN_STORM_BONUS[pawn_trop];
+    attack_power[color.as_index()] += looper.params.trophism_bonus[trop];
 
     if TRACE {
         let rank = color_adjust(opp_king_sq, color).rank();
         trace_safety_update!(t, EnemyKingRank, (rank), color, 1);
 
         trace_safety_update!(t, Tropism, (trop), color, 1);
-        trace_safety_update!(t, PawnStorm, (pawn_trop), color, 1);
     }
 
     score
@@ -556,9 +572,10 @@ pub fn mobility_threats_safety<const TRACE: bool>(
     board: &Board,
     us: Color,
     them: Color,
+    pawns: &PawnEval,
     t: &mut Trace,
 ) -> ScoreTuple {
-    let mut attack_power = [ScoreTuple::new(0, 0), ScoreTuple::new(0, 0)];
+    let mut attack_power = pawns.attack_power;
     let mut attack_info = [AttackInfo::default(), AttackInfo::default()];
 
     let us_virt_mob = virtual_mobility(board, us);
@@ -566,6 +583,7 @@ pub fn mobility_threats_safety<const TRACE: bool>(
 
     let mobility_and_threats = one_sided_eval::<TRACE>(
         board,
+        pawns,
         &mut attack_power,
         &mut attack_info,
         us_virt_mob,
@@ -575,6 +593,7 @@ pub fn mobility_threats_safety<const TRACE: bool>(
         t,
     ) - one_sided_eval::<TRACE>(
         board,
+        pawns,
         &mut attack_power,
         &mut attack_info,
         them_virt_mob,
@@ -584,13 +603,14 @@ pub fn mobility_threats_safety<const TRACE: bool>(
         t,
     );
 
-    safety_file_stucture::<TRACE>(board, &mut attack_power, t);
-
     let our_safe_contacts = safe_queen_contact_checks(board, &attack_info, us);
     let their_safe_contacts = safe_queen_contact_checks(board, &attack_info, them);
 
-    attack_power[us.as_index()] += STM_QUEEN_CONTACT_CHECKS.mult(our_safe_contacts);
-    attack_power[them.as_index()] += NON_STM_QUEEN_CONTACT_CHECKS.mult(their_safe_contacts);
+    let params = params();
+    attack_power[us.as_index()] += params.stm_queen_contact_checks.mult(our_safe_contacts);
+    attack_power[them.as_index()] += params
+        .non_stm_queen_contact_checks
+        .mult(their_safe_contacts);
 
     if TRACE {
         trace_safety_update!(t, StmQueenContactChecks, (), us, our_safe_contacts);
@@ -3,6 +3,7 @@ use crate::{
     eval::evaluation::ScoreTuple,
 };
 
+#[derive(Clone)]
 pub struct Pst {
     table: [[ScoreTuple; NUM_SQUARES as usize]; NUM_COLORS as usize],
 }
@@ -29,6 +30,7 @@ impl Pst {
     }
 }
 
+#[derive(Clone)]
 pub struct Prt {
     table: [[ScoreTuple; NUM_RANKS as usize]; NUM_COLORS as usize],
 }
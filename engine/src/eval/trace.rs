@@ -29,17 +29,23 @@ pub const SAFETY_TRACE_LEN: usize = Attacks::LEN
     + NonStmQueenContactChecks::LEN;
 
 pub struct Trace {
-    pub linear: [i8; LINEAR_TRACE_LEN],
+    pub linear: [[i8; LINEAR_TRACE_LEN]; NUM_COLORS as usize],
     pub safety: [[i8; SAFETY_TRACE_LEN]; NUM_COLORS as usize],
 }
 
 impl Trace {
     pub const fn empty() -> Self {
         Self {
-            linear: [0; LINEAR_TRACE_LEN],
+            linear: [[0; LINEAR_TRACE_LEN]; NUM_COLORS as usize],
             safety: [[0; SAFETY_TRACE_LEN]; NUM_COLORS as usize],
         }
     }
+
+    // white's linear features minus black's, which is what the tuner fits
+    pub fn linear_diff(&self) -> [i8; LINEAR_TRACE_LEN] {
+        let [white, black] = &self.linear;
+        std::array::from_fn(|i| white[i] - black[i])
+    }
 }
 
 pub const fn color_adjust(sq: Square, color: Color) -> Square {
@@ -52,24 +58,16 @@ pub const fn color_adjust(sq: Square, color: Color) -> Square {
 #[macro_export]
 macro_rules! trace_update {
     ($trace:ident, $name:ident, ($($arg:ident),*), $color:expr, $val:expr) => {{
-        let mult = match $color {
-            Color::White => 1,
-            Color::Black => -1,
-        };
         let index = $name::index($($arg,)*);
-        $trace.linear[index] += mult * ($val as i8);
+        $trace.linear[$color.as_index()][index] += $val as i8;
     }};
 }
 
 #[macro_export]
 macro_rules! trace_threat_update {
     ($trace:ident, $index_name:ident, $color:expr, $attacks:expr, $enemy:expr, $stm_index:expr) => {
-        let mult = match $color {
-            Color::White => 1,
-            Color::Black => -1,
-        };
         let val = ($attacks & $enemy).popcount();
-        $trace.linear[Threats::$index_name + $stm_index] += mult * (val as i8);
+        $trace.linear[$color.as_index()][Threats::$index_name + $stm_index] += val as i8;
     };
 }
 
@@ -239,7 +237,7 @@ impl PasserSqRule {
 pub struct Attacks;
 impl Attacks {
     pub const START: usize = 0;
-    const LEN: usize = (MoveCounts::QUEEN * (NUM_PIECES - 1) as usize);
+    pub const LEN: usize = (MoveCounts::QUEEN * (NUM_PIECES - 1) as usize);
 
     pub const fn index(piece: Piece, enemy_virt_mobility: usize) -> usize {
         Self::START + MoveCounts::QUEEN * piece.as_index() + enemy_virt_mobility
@@ -249,7 +247,7 @@ impl Attacks {
 pub struct Defenses;
 impl Defenses {
     pub const START: usize = Attacks::START + Attacks::LEN;
-    const LEN: usize = (MoveCounts::QUEEN * (NUM_PIECES - 1) as usize);
+    pub const LEN: usize = (MoveCounts::QUEEN * (NUM_PIECES - 1) as usize);
 
     pub const fn index(piece: Piece, virt_mobility: usize) -> usize {
         Self::START + MoveCounts::QUEEN * piece.as_index() + virt_mobility
@@ -4,7 +4,9 @@ use crate::{
     board::zobrist_stack::ZobristStack,
     create_thread_data,
     search::history_table::History,
-    search::search::Searcher,
+    search::search::{SearchOptions, Searcher},
+    search::search_stats::SearchStats,
+    search::thread_data::Nodes,
     search::transposition_table::TranspositionTable,
     tablebase::probe::Syzygy,
 };
@@ -12,12 +14,38 @@ use crate::{
 pub fn bench() {
     std::env::set_var("RUST_BACKTRACE", "1");
 
+    let (nodes, nps, stats) = run(SearchOptions::default());
+    println!("{nodes} nodes {nps} nps");
+    stats.print("");
+}
+
+// runs the bench with and without the eval cache, the node counts should match
+pub fn bench_eval_cache() {
+    std::env::set_var("RUST_BACKTRACE", "1");
+
+    let (nodes, nps, _) = run(SearchOptions::default());
+    println!("eval cache on:  {nodes} nodes {nps} nps");
+
+    let (nodes_off, nps_off, _) = run(SearchOptions {
+        no_eval_cache: true,
+        ..SearchOptions::default()
+    });
+    println!("eval cache off: {nodes_off} nodes {nps_off} nps");
+
+    if nodes != nodes_off {
+        println!("node counts differ!");
+    }
+    println!("relative speed: {}%", nps * 100 / nps_off);
+}
+
+fn run(options: SearchOptions) -> (Nodes, u128, SearchStats) {
     let positions: Vec<PerftTest> = test_postions();
 
     let stopwatch = std::time::Instant::now();
     let mut nodes = 0;
 
-    let tt = TranspositionTable::new(16);
+    let tt = TranspositionTable::new(16, 1);
+    let mut stats = SearchStats::new();
     for pos in positions {
         let board = Board::from_fen(pos.fen);
 
@@ -30,10 +58,12 @@ pub fn bench() {
             &tt,
             Syzygy::new(),
             thread_data,
+            options,
         );
         nodes += searcher.bench(&board, 15);
+        stats.merge(searcher.stats());
     }
 
     let nps = (u128::from(nodes) * 1_000_000) / stopwatch.elapsed().as_micros();
-    println!("{nodes} nodes {nps} nps");
+    (nodes, nps, stats)
 }
@@ -3,10 +3,92 @@ use crate::{
     search::search::{Depth, MAX_PLY},
 };
 
-const LMR_TABLE: [[Depth; MAX_MOVECOUNT]; MAX_PLY as usize] =
-    include!(concat!(env!("OUT_DIR"), "/lmr_init.rs"));
+type ReductionTable = [[Depth; MAX_MOVECOUNT]; MAX_PLY as usize];
 
+const LMR_TABLE: ReductionTable = include!(concat!(env!("OUT_DIR"), "/lmr_init.rs"));
+
+#[cfg(not(feature = "tune"))]
 #[allow(clippy::cast_sign_loss)]
 pub const fn get_reduction(depth: Depth, move_count: i32) -> Depth {
     LMR_TABLE[depth as usize][move_count as usize]
 }
+
+#[cfg(not(feature = "tune"))]
+pub const fn rebuild_table() {}
+
+#[cfg(feature = "tune")]
+mod runtime_table {
+    use std::sync::atomic::{AtomicI8, Ordering};
+
+    use super::{ReductionTable, LMR_TABLE};
+    use crate::{
+        board::chess_move::MAX_MOVECOUNT,
+        search::{
+            search::{Depth, MAX_PLY},
+            tunables::{lmr_base, lmr_divisor},
+        },
+    };
+
+    type AtomicReductionTable = [[AtomicI8; MAX_MOVECOUNT]; MAX_PLY as usize];
+
+    #[allow(clippy::large_stack_arrays)] // only evaluated at compile time
+    const fn atomic_table(table: &ReductionTable) -> AtomicReductionTable {
+        let mut result = [const { [const { AtomicI8::new(0) }; MAX_MOVECOUNT] }; MAX_PLY as usize];
+        let mut d = 0;
+        while d < MAX_PLY as usize {
+            let mut m = 0;
+            while m < MAX_MOVECOUNT {
+                result[d][m] = AtomicI8::new(table[d][m]);
+                m += 1;
+            }
+            d += 1;
+        }
+        result
+    }
+
+    static TABLE: AtomicReductionTable = atomic_table(&LMR_TABLE);
+
+    #[allow(clippy::cast_sign_loss)]
+    pub fn get_reduction(depth: Depth, move_count: i32) -> Depth {
+        TABLE[depth as usize][move_count as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn rebuild_table() {
+        let base = f64::from(lmr_base()) / 100.0;
+        let divisor = f64::from(lmr_divisor()) / 100.0;
+        for (d, row) in TABLE.iter().enumerate() {
+            for (m, r) in row.iter().enumerate() {
+                r.store(super::formula(d, m, base, divisor), Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tune")]
+pub use runtime_table::{get_reduction, rebuild_table};
+
+// same formula as lmr_init.rs in the build script
+#[cfg(any(test, feature = "tune"))]
+#[allow(clippy::cast_precision_loss)]
+fn formula(depth: usize, move_count: usize, base: f64, divisor: f64) -> Depth {
+    let depth = depth.max(1) as f64;
+    let move_count = move_count.max(1) as f64;
+    (base + depth.ln() * move_count.ln() / divisor) as Depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{formula, LMR_TABLE};
+    use crate::search::tunables::{lmr_base, lmr_divisor};
+
+    #[test]
+    fn runtime_formula_matches_build_script() {
+        let base = f64::from(lmr_base()) / 100.0;
+        let divisor = f64::from(lmr_divisor()) / 100.0;
+        for (d, row) in LMR_TABLE.iter().enumerate() {
+            for (m, &r) in row.iter().enumerate() {
+                assert_eq!(formula(d, m, base, divisor), r);
+            }
+        }
+    }
+}
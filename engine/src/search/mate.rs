@@ -0,0 +1,610 @@
+use std::{fs::read_to_string, time::Instant};
+
+use crate::board::{
+    board_representation::Board, chess_move::Move, movegen::MoveGenerator, zobrist::ZobristHash,
+    zobrist_stack::ZobristStack,
+};
+
+use super::{
+    search::{stop_flag_is_set, write_stop_flag},
+    thread_data::Nodes,
+};
+
+// A mate solver based on depth-first proof-number search (Nagai's df-pn). Unlike the
+// main search nothing is pruned, so a mate it reports is proven, and deepening one
+// attacker move at a time makes it the shortest one.
+//
+// OR nodes have the attacker to move and AND nodes the defender. Proof and disproof
+// numbers are always stored from the attacker's point of view, while the search itself
+// works with phi/delta, which are the (pn, dn) pair as seen by the side to move.
+type ProofNumber = u32;
+
+const INFINITY: ProofNumber = ProofNumber::MAX / 2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct ProofNumbers {
+    pn: ProofNumber,
+    dn: ProofNumber,
+    // mate length for proofs, otherwise the number of attacker moves searched
+    moves: u8,
+}
+
+impl ProofNumbers {
+    const UNKNOWN: Self = Self::new(1, 1, 0);
+
+    const fn new(pn: ProofNumber, dn: ProofNumber, moves: u8) -> Self {
+        Self { pn, dn, moves }
+    }
+
+    const fn proven(moves: u8) -> Self {
+        Self::new(0, INFINITY, moves)
+    }
+
+    const fn disproven(moves: u8) -> Self {
+        Self::new(INFINITY, 0, moves)
+    }
+
+    const fn is_proven(self) -> bool {
+        self.pn == 0
+    }
+
+    const fn phi(self, is_or: bool) -> ProofNumber {
+        if is_or {
+            self.pn
+        } else {
+            self.dn
+        }
+    }
+
+    const fn delta(self, is_or: bool) -> ProofNumber {
+        if is_or {
+            self.dn
+        } else {
+            self.pn
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct MateEntry {
+    key: u64,
+    numbers: ProofNumbers,
+    best_move: Move,
+    // nodes searched below this one, the replacement scheme keeps the costliest entries
+    work: u32,
+}
+
+impl MateEntry {
+    const EMPTY: Self = Self {
+        key: 0,
+        numbers: ProofNumbers::UNKNOWN,
+        best_move: Move::nullmove(),
+        work: 0,
+    };
+}
+
+type MateBucket = [MateEntry; MateTable::BUCKET_SIZE];
+
+struct MateTable {
+    buckets: Vec<MateBucket>,
+}
+
+impl MateTable {
+    const BUCKET_SIZE: usize = 2;
+
+    fn new(megabytes: usize) -> Self {
+        let len = (megabytes * 1024 * 1024 / std::mem::size_of::<MateBucket>()).max(1);
+        Self {
+            buckets: vec![[MateEntry::EMPTY; Self::BUCKET_SIZE]; len],
+        }
+    }
+
+    const fn index(&self, hash: ZobristHash) -> usize {
+        hash.as_usize() % self.buckets.len()
+    }
+
+    // A proof of a mate in n also proves every longer mate, and a disproof of a mate in n
+    // disproves every shorter one. Unsolved numbers only carry over to the same depth.
+    fn probe(&self, hash: ZobristHash, moves_left: u8) -> Option<MateEntry> {
+        let entry = *self.buckets[self.index(hash)]
+            .iter()
+            .find(|entry| entry.key == hash.as_u64())?;
+
+        let numbers = entry.numbers;
+        let usable = if numbers.pn == 0 {
+            numbers.moves <= moves_left
+        } else if numbers.dn == 0 {
+            numbers.moves >= moves_left
+        } else {
+            numbers.moves == moves_left
+        };
+
+        usable.then_some(entry)
+    }
+
+    // overwrites the entry of the same position, or else the one that took the least work
+    fn store(&mut self, hash: ZobristHash, numbers: ProofNumbers, best_move: Move, work: u32) {
+        let index = self.index(hash);
+        let bucket = &mut self.buckets[index];
+        let slot = bucket
+            .iter()
+            .position(|entry| entry.key == hash.as_u64())
+            .unwrap_or_else(|| {
+                (0..Self::BUCKET_SIZE)
+                    .min_by_key(|&i| bucket[i].work)
+                    .unwrap_or(0)
+            });
+        bucket[slot] = MateEntry {
+            key: hash.as_u64(),
+            numbers,
+            best_move,
+            work,
+        };
+    }
+}
+
+struct Child {
+    mv: Move,
+    hash: ZobristHash,
+    // set for children that need no search: mates, non-mates at the horizon and repetitions,
+    // and for disproofs that only hold on the current path
+    fixed: Option<ProofNumbers>,
+    // for a disproof that relies on a repetition, how many plies above the parent the
+    // repeated position is, or 0 if it holds however the parent is reached
+    path_plies: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MateResult {
+    pub moves: u8,
+    pub pv: Vec<Move>,
+}
+
+pub struct MateSolver {
+    table: MateTable,
+    zobrist_stack: ZobristStack,
+    nodes: Nodes,
+    node_limit: Nodes,
+    stopped: bool,
+}
+
+impl MateSolver {
+    const STOP_CHECK_FREQ: Nodes = 1024;
+
+    pub fn new(megabytes: usize, zobrist_stack: &ZobristStack) -> Self {
+        Self {
+            table: MateTable::new(megabytes),
+            zobrist_stack: zobrist_stack.clone(),
+            nodes: 0,
+            node_limit: Nodes::MAX,
+            stopped: false,
+        }
+    }
+
+    pub const fn set_node_limit(&mut self, node_limit: Nodes) {
+        self.node_limit = node_limit;
+    }
+
+    pub const fn nodes(&self) -> Nodes {
+        self.nodes
+    }
+
+    // Finds the shortest mate for the side to move of at most max_moves moves. With
+    // report_info set, this prints UCI info lines and the bestmove.
+    pub fn go(&mut self, board: &Board, max_moves: u8, report_info: bool) -> Option<MateResult> {
+        let stopwatch = Instant::now();
+        let mut result = None;
+
+        for moves in 1..=max_moves {
+            let (numbers, _) = self.mid(board, moves, true, INFINITY, INFINITY);
+            if self.stopped {
+                break;
+            }
+
+            if report_info {
+                let elapsed = stopwatch.elapsed();
+                let nps = (u128::from(self.nodes) * 1_000_000) / elapsed.as_micros().max(1);
+                let mate_str = if numbers.is_proven() {
+                    format!(" score mate {}", numbers.moves)
+                } else {
+                    String::new()
+                };
+                let pv = self.pv(board, moves);
+                println!(
+                    "info depth {moves}{mate_str} nodes {} time {} nps {nps} pv {}",
+                    self.nodes,
+                    elapsed.as_millis(),
+                    pv.iter()
+                        .map(|mv| mv.as_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                );
+            }
+
+            if numbers.is_proven() {
+                result = Some(MateResult {
+                    moves: numbers.moves,
+                    pv: self.pv(board, moves),
+                });
+                break;
+            }
+        }
+        write_stop_flag(true);
+
+        if report_info {
+            let best_move = result.as_ref().map_or_else(
+                || MoveGenerator::first_legal_move(board).unwrap_or(Move::nullmove()),
+                |r| r.pv[0],
+            );
+            if result.is_none() {
+                println!("info string no mate in {max_moves} found");
+            }
+            println!("bestmove {}", best_move.as_string());
+        }
+
+        result
+    }
+
+    fn lookup(&self, hash: ZobristHash, moves_left: u8) -> ProofNumbers {
+        self.table
+            .probe(hash, moves_left)
+            .map_or(ProofNumbers::UNKNOWN, |entry| entry.numbers)
+    }
+
+    fn child_numbers(&self, child: &Child, moves_left: u8) -> ProofNumbers {
+        child
+            .fixed
+            .unwrap_or_else(|| self.lookup(child.hash, moves_left))
+    }
+
+    // Generates the legal moves, evaluating the children that are already decided. An
+    // AND child with no attacker moves left is a leaf: either it is mate, or it failed.
+    fn generate_children(&mut self, board: &Board, moves_left: u8, is_or: bool) -> Vec<Child> {
+        let hash_base = ZobristHash::incremental_update_base(board);
+        let mut generator = MoveGenerator::new();
+        let mut children = vec![];
+
+        while let Some(mv) = generator.simple_next::<true>(board) {
+            let mut child_board = board.clone();
+            if !child_board.try_play_move(mv, &mut self.zobrist_stack, hash_base) {
+                continue;
+            }
+
+            let repetition = self
+                .zobrist_stack
+                .repetition_distance(child_board.halfmoves);
+            let fixed = if repetition.is_some() {
+                Some(ProofNumbers::disproven(moves_left))
+            } else if is_or && moves_left == 1 {
+                if child_board.in_check() && MoveGenerator::no_legal_moves(&child_board) {
+                    Some(ProofNumbers::proven(0))
+                } else {
+                    Some(ProofNumbers::disproven(0))
+                }
+            } else {
+                None
+            };
+            let hash = self.zobrist_stack.current_zobrist_hash();
+            self.zobrist_stack.revert_state();
+
+            children.push(Child {
+                mv,
+                hash,
+                fixed,
+                path_plies: repetition.map_or(0, |distance| distance - 1),
+            });
+        }
+
+        children
+    }
+
+    // Repetitions only ever help the defender, so proofs hold wherever the position is
+    // reached, but a disproof that relies on one only holds on paths through the repeated
+    // position. Returns how many plies above this node that is, or 0 if it's in the subtree.
+    fn disproof_path_plies(&self, children: &[Child], moves_left: u8, is_or: bool) -> usize {
+        if is_or {
+            children
+                .iter()
+                .map(|child| child.path_plies)
+                .max()
+                .unwrap_or(0)
+        } else {
+            // the defender needs just one of the refutations
+            children
+                .iter()
+                .filter(|child| self.child_numbers(child, moves_left).dn == 0)
+                .map(|child| child.path_plies)
+                .min()
+                .unwrap_or(0)
+        }
+    }
+
+    // moves_left is the number of attacker moves still available, including the current
+    // one at OR nodes. Disproofs that only hold on the current path aren't stored, instead
+    // they're returned with how far up the path they go.
+    fn mid(
+        &mut self,
+        board: &Board,
+        moves_left: u8,
+        is_or: bool,
+        phi_threshold: ProofNumber,
+        delta_threshold: ProofNumber,
+    ) -> (ProofNumbers, usize) {
+        let start_nodes = self.nodes;
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(Self::STOP_CHECK_FREQ) && stop_flag_is_set()
+            || self.nodes >= self.node_limit
+        {
+            self.stopped = true;
+        }
+
+        let hash = self.zobrist_stack.current_zobrist_hash();
+        let mut children = self.generate_children(board, moves_left, is_or);
+        if children.is_empty() {
+            // checkmate counts for the attacker, stalemate never does
+            let numbers = if !is_or && board.in_check() {
+                ProofNumbers::proven(0)
+            } else {
+                ProofNumbers::disproven(moves_left)
+            };
+            self.table.store(hash, numbers, Move::nullmove(), 1);
+            return (numbers, 0);
+        }
+
+        let child_moves_left = if is_or { moves_left - 1 } else { moves_left };
+        let hash_base = ZobristHash::incremental_update_base(board);
+        loop {
+            let mut phi = INFINITY;
+            let mut delta: ProofNumber = 0;
+            let mut best = 0;
+            let mut second_best_delta = INFINITY;
+            for (i, child) in children.iter().enumerate() {
+                let numbers = self.child_numbers(child, child_moves_left);
+                let child_phi = numbers.phi(!is_or);
+                let child_delta = numbers.delta(!is_or);
+
+                if child_delta < phi {
+                    second_best_delta = phi;
+                    phi = child_delta;
+                    best = i;
+                } else if child_delta < second_best_delta {
+                    second_best_delta = child_delta;
+                }
+                delta = delta.saturating_add(child_phi).min(INFINITY);
+            }
+
+            if phi == 0
+                || delta == 0
+                || phi >= phi_threshold
+                || delta >= delta_threshold
+                || self.stopped
+            {
+                let numbers = self.node_numbers(&children, moves_left, is_or, phi, delta);
+                let path_plies = if numbers.dn == 0 {
+                    self.disproof_path_plies(&children, child_moves_left, is_or)
+                } else {
+                    0
+                };
+                if path_plies == 0 {
+                    let work = u32::try_from(self.nodes - start_nodes).unwrap_or(u32::MAX);
+                    self.table.store(hash, numbers, children[best].mv, work);
+                }
+                return (numbers, path_plies);
+            }
+
+            let child = &mut children[best];
+            let best_phi = self.child_numbers(child, child_moves_left).phi(!is_or);
+            let child_phi_threshold = delta_threshold - delta + best_phi;
+            let child_delta_threshold = phi_threshold.min(second_best_delta.saturating_add(1));
+
+            let mut child_board = board.clone();
+            child_board.try_play_move(child.mv, &mut self.zobrist_stack, hash_base);
+            let (numbers, path_plies) = self.mid(
+                &child_board,
+                child_moves_left,
+                !is_or,
+                child_phi_threshold,
+                child_delta_threshold,
+            );
+            self.zobrist_stack.revert_state();
+            if path_plies > 0 {
+                child.fixed = Some(numbers);
+                child.path_plies = path_plies - 1;
+            }
+        }
+    }
+
+    // converts phi/delta back to proof numbers, working out the mate length of a proof
+    fn node_numbers(
+        &self,
+        children: &[Child],
+        moves_left: u8,
+        is_or: bool,
+        phi: ProofNumber,
+        delta: ProofNumber,
+    ) -> ProofNumbers {
+        let (pn, dn) = if is_or { (phi, delta) } else { (delta, phi) };
+        if dn == 0 {
+            return ProofNumbers::disproven(moves_left);
+        } else if pn != 0 {
+            return ProofNumbers::new(pn, dn, moves_left);
+        }
+
+        let child_moves_left = if is_or { moves_left - 1 } else { moves_left };
+        let proven_lengths = children
+            .iter()
+            .map(|child| self.child_numbers(child, child_moves_left))
+            .filter(|numbers| numbers.is_proven())
+            .map(|numbers| numbers.moves);
+
+        // the attacker picks the fastest mate, and the defender the slowest
+        if is_or {
+            ProofNumbers::proven(proven_lengths.min().unwrap_or(0) + 1)
+        } else {
+            ProofNumbers::proven(proven_lengths.max().unwrap_or(0))
+        }
+    }
+
+    // Follows the proof through the table, with the defender always choosing the reply
+    // that delays mate the longest.
+    fn pv(&mut self, board: &Board, moves: u8) -> Vec<Move> {
+        let mut pv = vec![];
+        let mut board = board.clone();
+        let mut moves_left = moves;
+        let mut is_or = true;
+        let depth = self.zobrist_stack.clone();
+
+        loop {
+            let hash = self.zobrist_stack.current_zobrist_hash();
+            let mv = if is_or {
+                match self.table.probe(hash, moves_left) {
+                    Some(entry) if entry.numbers.is_proven() && moves_left > 0 => entry.best_move,
+                    _ => break,
+                }
+            } else {
+                let children = self.generate_children(&board, moves_left, false);
+                let slowest = children
+                    .iter()
+                    .map(|child| (child.mv, self.child_numbers(child, moves_left)))
+                    .filter(|(_, numbers)| numbers.is_proven())
+                    .max_by_key(|(_, numbers)| numbers.moves);
+                match slowest {
+                    Some((mv, _)) => mv,
+                    None => break,
+                }
+            };
+
+            let hash_base = ZobristHash::incremental_update_base(&board);
+            if mv.is_null() || !board.try_play_move(mv, &mut self.zobrist_stack, hash_base) {
+                break;
+            }
+
+            pv.push(mv);
+            if is_or {
+                moves_left -= 1;
+            }
+            is_or = !is_or;
+        }
+
+        self.zobrist_stack = depth;
+        pv
+    }
+}
+
+// Runs the solver on every position in an EPD file with a "dm" (direct mate) opcode, and
+// checks that the shortest mate matches.
+pub fn solve_epd(file_name: &str, megabytes: usize, node_limit: Nodes) {
+    let stopwatch = Instant::now();
+    let mut total_nodes = 0;
+    let mut solved = 0;
+    let mut total = 0;
+
+    for line in read_to_string(file_name)
+        .expect("expected EPD file to read!")
+        .lines()
+    {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 4 {
+            continue;
+        }
+
+        let fen = fields[..4].join(" ");
+        let operations = fields[4..].join(" ");
+        let Some(expected) = operations
+            .split(';')
+            .filter_map(|op| op.trim().strip_prefix("dm "))
+            .find_map(|n| n.trim().parse::<u8>().ok())
+        else {
+            continue;
+        };
+
+        let board = Board::from_fen(&fen);
+        let mut solver = MateSolver::new(megabytes, &ZobristStack::new(&board));
+        solver.set_node_limit(node_limit);
+
+        write_stop_flag(false);
+        let result = solver.go(&board, expected, false);
+        total_nodes += solver.nodes();
+        total += 1;
+
+        let status = match &result {
+            Some(r) if r.moves == expected => {
+                solved += 1;
+                "ok".to_owned()
+            }
+            Some(r) => format!("FAILED, found mate in {}", r.moves),
+            None => "FAILED, no mate found".to_owned(),
+        };
+        let pv = result.map_or_else(String::new, |r| {
+            r.pv.iter()
+                .map(|mv| mv.as_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        println!(
+            "{fen} dm {expected}: {status} ({} nodes) {pv}",
+            solver.nodes()
+        );
+    }
+
+    let nps = (u128::from(total_nodes) * 1_000_000) / stopwatch.elapsed().as_micros().max(1);
+    println!("{solved}/{total} solved, {total_nodes} nodes {nps} nps");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MateSolver;
+    use crate::board::{
+        board_representation::Board, chess_move::Move, zobrist_stack::ZobristStack,
+    };
+
+    fn solve(fen: &str, max_moves: u8) -> Option<(u8, Move)> {
+        let board = Board::from_fen(fen);
+        let mut solver = MateSolver::new(4, &ZobristStack::new(&board));
+        solver
+            .go(&board, max_moves, false)
+            .map(|result| (result.moves, result.pv[0]))
+    }
+
+    #[test]
+    fn finds_shortest_mates() {
+        // back rank mate in 1
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1";
+        let board = Board::from_fen(fen);
+        assert_eq!(solve(fen, 3), Some((1, Move::from_string("a1a8", &board))));
+
+        // king and queen, mate in 2 and mate in 3
+        assert_eq!(
+            solve("8/k7/2K5/8/8/8/8/2Q5 w - - 0 1", 5).map(|r| r.0),
+            Some(2)
+        );
+        assert_eq!(
+            solve("k7/8/8/1K6/8/8/8/2Q5 w - - 0 1", 5).map(|r| r.0),
+            Some(3)
+        );
+        assert_eq!(solve("k7/8/8/1K6/8/8/8/2Q5 w - - 0 1", 2), None);
+    }
+
+    #[test]
+    fn stalemate_is_not_mate() {
+        // Qc7 stalemates, and there is no mate in 1
+        assert_eq!(solve("k7/8/8/1K6/8/8/8/2Q5 w - - 0 1", 1), None);
+    }
+
+    #[test]
+    fn node_limit_stops_a_thrashing_table() {
+        // a table of a single bucket keeps losing entries, but the limit still ends the search
+        // (kept below the stop flag checks, which other tests may have left set)
+        let board = Board::from_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1");
+        let mut solver = MateSolver::new(0, &ZobristStack::new(&board));
+        solver.set_node_limit(1_000);
+        assert_eq!(solver.go(&board, 9, false), None);
+        assert_eq!(solver.nodes(), 1_000);
+
+        // and mates that need no table are still found
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1");
+        let mut solver = MateSolver::new(0, &ZobristStack::new(&board));
+        let result = solver.go(&board, 3, false).map(|r| (r.moves, r.pv[0]));
+        assert_eq!(result, Some((1, Move::from_string("a1a8", &board))));
+    }
+}
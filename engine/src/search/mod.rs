@@ -1,12 +1,17 @@
 #![allow(clippy::module_inception)]
 pub mod bench;
 pub mod history_table;
-mod improving;
-mod killers;
 mod late_move_reductions;
+pub mod mate;
 mod pv_table;
 pub mod search;
+mod search_stack;
+pub mod search_stats;
+pub mod search_tree;
 mod see;
+pub mod skill;
 pub mod thread_data;
 pub mod time_management;
 pub mod transposition_table;
+mod tt_memory;
+pub mod tunables;
@@ -1,28 +1,41 @@
 use std::{
     sync::atomic::{AtomicBool, Ordering},
-    time::Instant,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use arrayvec::ArrayVec;
 
 use super::{
     history_table::History,
-    improving::EvalStack,
-    killers::Killers,
     late_move_reductions::get_reduction,
     pv_table::PvTable,
+    search_stack::SearchStack,
+    search_stats::{SearchStats, Stat},
+    search_tree::{Prune, SearchTree},
+    see::SEE_VALS,
+    skill::Skill,
     thread_data::{Nodes, ThreadData},
     time_management::{Milliseconds, SearchTimer},
     transposition_table::{TTFlag, TranspositionTable},
+    tunables::{
+        asp_window_full_search_bounds, asp_window_init_delta, asp_window_init_window,
+        asp_window_min_depth, delta_margin, fp_base, fp_margin, fp_max_depth, iir_min_depth,
+        lmr_min_depth, nmp_min_depth, probcut_margin, probcut_min_depth, probcut_reduction,
+        pruning_threshold, razor_margin, razor_max_depth, rfp_margin, rfp_min_depth, see_margin,
+        see_min_depth,
+    },
 };
 
 use crate::{
-    board::board_representation::Board,
-    board::chess_move::{Move, MAX_MOVECOUNT},
+    board::board_representation::{Board, Piece},
+    board::chess_move::{Flag, Move, MAX_MOVECOUNT},
     board::movegen::MoveGenerator,
     board::{board_representation::NUM_SQUARES, zobrist_stack::ZobristStack},
     board::{movegen::MoveStage, zobrist::ZobristHash},
-    eval::evaluation::{evaluate, EvalScore, EVAL_MAX, INF, MATE_THRESHOLD},
+    eval::eval_cache::EvalCache,
+    eval::evaluation::{evaluate_cached, EvalScore, EVAL_MAX, INF, MATE_THRESHOLD},
+    eval::pawn_table::PawnTable,
+    eval::wdl::{self, Wdl},
     tablebase::probe::Syzygy,
 };
 
@@ -63,25 +76,61 @@ pub enum SearchLimit {
     Nodes(Nodes),
 }
 
+#[derive(Debug, Default, Copy, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SearchOptions {
+    pub show_wdl: bool,
+    pub normalize_eval: bool,
+    pub contempt: EvalScore,
+    pub skill: Skill,
+    pub nodes_time: Nodes,
+    // single threaded with a node clock, so that output only depends on the input
+    pub deterministic: bool,
+    // limits for recording the search tree, only used with the "search-tree" feature
+    pub tree_ply: Ply,
+    pub tree_nodes: usize,
+    // for testing and benchmarking, the static eval is always recomputed
+    pub no_eval_cache: bool,
+    // only captures at the first qsearch ply, without the quiet checks
+    pub no_qsearch_checks: bool,
+}
+
 #[derive(Debug)]
 pub struct Searcher<'a> {
     search_limits: Vec<SearchLimit>,
     zobrist_stack: ZobristStack,
     pv_table: PvTable,
     history: History,
-    killers: Killers,
-    eval_stack: EvalStack,
+    stack: SearchStack,
+    pawn_table: PawnTable,
+    eval_cache: EvalCache,
     tt: &'a TranspositionTable,
     tb: Syzygy,
 
     thread_data: ThreadData<'a>,
     timer: Option<SearchTimer>,
     seldepth: u8,
+    options: SearchOptions,
+
+    report_info: bool,
+    stopwatch: Instant,
+    last_report: Milliseconds,
+
+    root_scores: Vec<(Move, EvalScore)>,
+    skill_seed: u64,
+
+    stats: SearchStats,
+    tree: SearchTree,
 }
 
 impl<'a> Searcher<'a> {
     const TIMER_CHECK_FREQ: u64 = 1024;
 
+    // don't flood the GUI with output during the first moments of a search
+    const BOUND_REPORT_MIN_TIME: Milliseconds = 1000;
+    const CURRMOVE_REPORT_MIN_TIME: Milliseconds = 1000;
+    const HEARTBEAT_INTERVAL: Milliseconds = 2000;
+
     pub fn new(
         search_limits: Vec<SearchLimit>,
         zobrist_stack: &ZobristStack,
@@ -89,32 +138,69 @@ impl<'a> Searcher<'a> {
         tt: &'a TranspositionTable,
         tb: Syzygy,
         thread_data: ThreadData<'a>,
+        options: SearchOptions,
     ) -> Self {
         Self {
             search_limits,
             zobrist_stack: zobrist_stack.clone(),
             history: history.clone(),
-            killers: Killers::new(),
-            eval_stack: EvalStack::new(),
+            stack: SearchStack::new(),
+            pawn_table: PawnTable::new(),
+            eval_cache: EvalCache::new(),
             tt,
             tb,
             pv_table: PvTable::new(),
             thread_data,
             timer: None,
             seldepth: 0,
+            options,
+            report_info: false,
+            stopwatch: Instant::now(),
+            last_report: 0,
+            root_scores: vec![],
+            skill_seed: 0,
+            stats: SearchStats::new(),
+            tree: SearchTree::new(options.tree_ply, options.tree_nodes),
         }
     }
 
+    pub const fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    pub const fn tree(&self) -> &SearchTree {
+        &self.tree
+    }
+
+    pub fn nodes(&self) -> Nodes {
+        self.thread_data.combined().0
+    }
+
     pub fn search_complete_actions(&self, uci_history: &mut History) {
         *uci_history = self.history.clone();
         uci_history.age_scores();
     }
 
-    fn report_search_info(&self, score: EvalScore, depth: Depth, stopwatch: Instant) {
+    // the node clock stands in for real time in deterministic mode, so reports are repeatable
+    fn elapsed_micros(&self) -> u128 {
+        if self.options.deterministic && self.options.nodes_time > 0 {
+            u128::from(self.thread_data.combined().0) * 1000 / u128::from(self.options.nodes_time)
+        } else {
+            self.stopwatch.elapsed().as_micros()
+        }
+    }
+
+    fn elapsed_millis(&self) -> Milliseconds {
+        self.elapsed_micros() / 1000
+    }
+
+    fn report_search_info(&mut self, board: &Board, score: EvalScore, depth: Depth, bound: TTFlag) {
         let (nodes, tb_hits) = self.thread_data.combined();
+        let material = wdl::material(board);
 
-        let elapsed = stopwatch.elapsed();
-        let nps = (u128::from(nodes) * 1_000_000) / elapsed.as_micros().max(1);
+        let elapsed = self.elapsed_micros();
+        let nps = (u128::from(nodes) * 1_000_000) / elapsed.max(1);
+        self.last_report = elapsed / 1000;
 
         let score_str = if score >= MATE_THRESHOLD {
             let ply = EVAL_MAX - score;
@@ -126,14 +212,29 @@ impl<'a> Searcher<'a> {
             let score_value = (ply + 1) / 2;
 
             format!("mate -{score_value}")
+        } else if self.options.normalize_eval {
+            format!("cp {}", wdl::normalize(score, material))
         } else {
             format!("cp {score}")
         };
 
+        let bound_str = match bound {
+            TTFlag::LOWER_BOUND => " lowerbound",
+            TTFlag::UPPER_BOUND => " upperbound",
+            _ => "",
+        };
+
+        let wdl_str = if self.options.show_wdl {
+            let Wdl { win, draw, loss } = wdl::wdl(score, material);
+            format!(" wdl {win} {draw} {loss}")
+        } else {
+            String::new()
+        };
+
         println!(
-            "info score {score_str} nodes {} time {} nps {nps} depth {depth} seldepth {} hashfull {} tbhits {} pv {}",
+            "info score {score_str}{bound_str}{wdl_str} nodes {} time {} nps {nps} depth {depth} seldepth {} hashfull {} tbhits {} pv {}",
             nodes,
-            elapsed.as_millis(),
+            elapsed / 1000,
             self.seldepth,
             self.tt.hashfull(),
             tb_hits,
@@ -141,6 +242,19 @@ impl<'a> Searcher<'a> {
         );
     }
 
+    fn report_heartbeat(&mut self) {
+        let (nodes, _) = self.thread_data.combined();
+        let elapsed = self.elapsed_micros();
+        let nps = (u128::from(nodes) * 1_000_000) / elapsed.max(1);
+        self.last_report = elapsed / 1000;
+
+        println!(
+            "info nodes {nodes} nps {nps} hashfull {} time {}",
+            self.tt.hashfull(),
+            elapsed / 1000
+        );
+    }
+
     fn tb_root_report(search_results: SearchResults) {
         println!(
             "info score cp {} depth 1 seldepth 1 nodes 1 nps 1 tbhits 1 pv {}",
@@ -162,7 +276,10 @@ impl<'a> Searcher<'a> {
         let mut result = false;
         for &limit in &self.search_limits {
             result |= match limit {
-                SearchLimit::Time(_) => self.timer.unwrap().is_soft_expired(),
+                SearchLimit::Time(_) => self
+                    .timer
+                    .unwrap()
+                    .is_soft_expired(self.thread_data.combined().0),
                 SearchLimit::Depth(depth_limit) => depth > depth_limit,
                 SearchLimit::Nodes(node_limit) => self.thread_data.thread_node_count() > node_limit,
             }
@@ -171,10 +288,55 @@ impl<'a> Searcher<'a> {
         result
     }
 
-    fn is_out_of_time(&self) -> bool {
+    // a positive contempt makes the root side treat draws as a loss of that many centipawns
+    const fn contempt(&self, ply: Ply) -> EvalScore {
+        if ply & 1 == 0 {
+            -self.options.contempt
+        } else {
+            self.options.contempt
+        }
+    }
+
+    fn draw_score(&self, ply: Ply) -> EvalScore {
+        // jitter by one centipawn based on the node count, so that the search doesn't
+        // blindly settle for a draw without looking at the alternatives
+        let jitter = 1 - (self.thread_data.thread_node_count() & 2) as EvalScore;
+        self.contempt(ply) + jitter
+    }
+
+    fn evaluate(&mut self, board: &Board) -> EvalScore {
+        let hash = self.zobrist_stack.current_zobrist_hash();
+        let eval = if self.options.no_eval_cache {
+            evaluate_cached(board, &mut self.pawn_table)
+        } else if let Some(eval) = self.eval_cache.get(hash) {
+            eval
+        } else {
+            let eval = evaluate_cached(board, &mut self.pawn_table);
+            self.eval_cache.store(hash, eval);
+            eval
+        };
+
+        if self.options.skill.is_enabled() {
+            eval + self
+                .options
+                .skill
+                .eval_noise(hash.as_u64(), self.skill_seed)
+        } else {
+            eval
+        }
+    }
+
+    fn is_out_of_time(&mut self) -> bool {
         if self.thread_data.thread_node_count() % Self::TIMER_CHECK_FREQ == 0 {
+            if self.report_info
+                && self.elapsed_millis().saturating_sub(self.last_report)
+                    >= Self::HEARTBEAT_INTERVAL
+            {
+                self.report_heartbeat();
+            }
+
             if let Some(timer) = self.timer {
-                return timer.is_expired();
+                return timer.is_expired(self.thread_data.combined().0);
             }
         }
 
@@ -191,6 +353,8 @@ impl<'a> Searcher<'a> {
                 &mut Move::nullmove(),
                 &mut vec![],
             );
+            self.stats
+                .end_iteration(self.thread_data.thread_node_count());
         }
 
         self.thread_data.thread_node_count()
@@ -204,10 +368,22 @@ impl<'a> Searcher<'a> {
         if IS_PRIMARY {
             for &limit in &self.search_limits {
                 if let SearchLimit::Time(t) = limit {
-                    self.timer = Some(SearchTimer::new(t));
+                    self.timer = Some(SearchTimer::new(t, self.options.nodes_time));
                 }
             }
 
+            if self.options.skill.is_enabled() {
+                self.search_limits
+                    .push(SearchLimit::Nodes(self.options.skill.node_limit()));
+                self.skill_seed = if self.options.deterministic {
+                    0
+                } else {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_nanos() as u64)
+                };
+            }
+
             if let Some((best_move, score)) = self.tb.probe_root(board) {
                 let results = SearchResults { best_move, score };
                 if report_info {
@@ -218,15 +394,20 @@ impl<'a> Searcher<'a> {
             }
         }
 
-        let stopwatch = std::time::Instant::now();
+        self.report_info = report_info;
+        self.stopwatch = Instant::now();
+        self.last_report = 0;
         let mut depth: Depth = 1;
 
         let mut search_results = SearchResults::new(board);
+        let mut completed_root_scores = vec![];
         let mut widenings = vec![];
+        let mut best_move_stability = 0;
         let mut move_node_table: Box<[[Nodes; NUM_SQUARES as usize]; NUM_SQUARES as usize]> =
             Box::new([[0; NUM_SQUARES as usize]; NUM_SQUARES as usize]);
         while !self.stop_searching::<IS_PRIMARY>(depth) {
             self.seldepth = 0;
+            self.tree.start_iteration();
 
             let prev_nodecount = self.thread_data.thread_node_count();
             let score = self.aspiration_window_search(
@@ -240,12 +421,23 @@ impl<'a> Searcher<'a> {
             if stop_flag_is_set() {
                 break;
             }
+            self.stats
+                .end_iteration(self.thread_data.thread_node_count());
 
             if report_info {
-                self.report_search_info(score, depth, stopwatch);
+                self.report_search_info(board, score, depth, TTFlag::EXACT);
             }
-            search_results.best_move = self.pv_table.best_move();
+            let best_move = self.pv_table.best_move();
+            best_move_stability = if best_move == search_results.best_move {
+                best_move_stability + 1
+            } else {
+                0
+            };
+            let score_drop = search_results.score - score;
+
+            search_results.best_move = best_move;
             search_results.score = score;
+            completed_root_scores.clone_from(&self.root_scores);
 
             self.search_time_adjustment(
                 &mut widenings,
@@ -253,12 +445,21 @@ impl<'a> Searcher<'a> {
                 prev_nodecount,
                 depth,
                 search_results.best_move,
+                best_move_stability,
+                score_drop,
             );
 
             depth += 1;
         }
         write_stop_flag(true);
 
+        if self.options.skill.is_enabled() && !completed_root_scores.is_empty() {
+            search_results.best_move = self
+                .options
+                .skill
+                .pick_move(&completed_root_scores, self.skill_seed);
+        }
+
         assert!(
             search_results.best_move.to() != search_results.best_move.from(),
             "INVALID MOVE"
@@ -271,7 +472,7 @@ impl<'a> Searcher<'a> {
         search_results
     }
 
-    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_precision_loss, clippy::too_many_arguments)]
     fn search_time_adjustment(
         &mut self,
         widenings: &mut Vec<u16>,
@@ -279,6 +480,8 @@ impl<'a> Searcher<'a> {
         prev_nodecount: Nodes,
         depth: Depth,
         best_move: Move,
+        best_move_stability: u16,
+        score_drop: EvalScore,
     ) {
         if let Some(timer) = &mut self.timer {
             let total_nodes = self.thread_data.thread_node_count();
@@ -291,7 +494,12 @@ impl<'a> Searcher<'a> {
             if depth >= TM_UPDATE_DEPTH {
                 let best_move_node_fraction = *best_move_count as f64 / total_nodes as f64;
 
-                timer.update_soft_limit(widenings.as_slice(), best_move_node_fraction);
+                timer.update_soft_limit(
+                    widenings.as_slice(),
+                    best_move_node_fraction,
+                    best_move_stability,
+                    score_drop,
+                );
             }
         }
     }
@@ -304,34 +512,42 @@ impl<'a> Searcher<'a> {
         best_move: &mut Move,
         widenings: &mut Vec<u16>,
     ) -> EvalScore {
-        const ASP_WINDOW_MIN_DEPTH: Depth = 7;
-        const ASP_WINDOW_INIT_WINDOW: EvalScore = 12;
-        const ASP_WINDOW_INIT_DELTA: EvalScore = 16;
-        const ASP_WINDOW_FULL_SEARCH_BOUNDS: EvalScore = 3500;
-
         let mut alpha = -INF;
         let mut beta = INF;
         let mut asp_depth = current_depth;
-        let mut delta = ASP_WINDOW_INIT_DELTA;
+        let mut delta = asp_window_init_delta();
 
-        if current_depth > ASP_WINDOW_MIN_DEPTH {
-            alpha = (prev_score - ASP_WINDOW_INIT_WINDOW).max(-INF);
-            beta = (prev_score + ASP_WINDOW_INIT_WINDOW).min(INF);
+        if current_depth > asp_window_min_depth() {
+            alpha = (prev_score - asp_window_init_window()).max(-INF);
+            beta = (prev_score + asp_window_init_window()).min(INF);
         } else {
             return self.negamax::<true, false>(board, asp_depth, 0, alpha, beta);
         }
 
         let mut w = 0;
         loop {
-            if alpha < -ASP_WINDOW_FULL_SEARCH_BOUNDS {
+            if alpha < -asp_window_full_search_bounds() {
                 alpha = -INF;
             }
-            if beta > ASP_WINDOW_FULL_SEARCH_BOUNDS {
+            if beta > asp_window_full_search_bounds() {
                 beta = INF;
             }
 
             let score = self.negamax::<true, false>(board, asp_depth, 0, alpha, beta);
 
+            if (score <= alpha || score >= beta)
+                && self.report_info
+                && !stop_flag_is_set()
+                && self.elapsed_millis() >= Self::BOUND_REPORT_MIN_TIME
+            {
+                let bound = if score <= alpha {
+                    TTFlag::UPPER_BOUND
+                } else {
+                    TTFlag::LOWER_BOUND
+                };
+                self.report_search_info(board, score, current_depth, bound);
+            }
+
             if score <= alpha {
                 alpha = (alpha - delta).max(-INF);
                 beta = (alpha + 3 * beta) / 4;
@@ -352,9 +568,23 @@ impl<'a> Searcher<'a> {
         }
     }
 
+    fn negamax<const IS_ROOT: bool, const DO_NULL_MOVE: bool>(
+        &mut self,
+        board: &Board,
+        depth: Depth,
+        ply: Ply,
+        alpha: EvalScore,
+        beta: EvalScore,
+    ) -> EvalScore {
+        self.tree.enter(ply, depth, alpha, beta, false);
+        let score = self.search_node::<IS_ROOT, DO_NULL_MOVE>(board, depth, ply, alpha, beta);
+        self.tree.exit(score);
+        score
+    }
+
     #[rustfmt::skip]
     #[allow(clippy::cognitive_complexity)] // lol
-    fn negamax<const IS_ROOT: bool, const DO_NULL_MOVE: bool>(
+    fn search_node<const IS_ROOT: bool, const DO_NULL_MOVE: bool>(
         &mut self,
         board: &Board,
         mut depth: Depth,
@@ -363,6 +593,9 @@ impl<'a> Searcher<'a> {
         beta: EvalScore,
     ) -> EvalScore {
         self.pv_table.set_length(ply);
+        let excluded = self.stack.at(ply).excluded;
+        self.stack.at_mut(ply).double_extensions =
+            if IS_ROOT { 0 } else { self.stack.back(ply, 1).double_extensions };
 
         let old_alpha = alpha;
         let in_check = board.in_check();
@@ -372,13 +605,25 @@ impl<'a> Searcher<'a> {
 
         if !IS_ROOT {
             if is_drawn {
-                return 0;
+                self.tree.set_prune(Prune::Draw);
+                return self.draw_score(ply);
+            }
+
+            // UPCOMING REPETITION DETECTION
+            let draw_score = self.draw_score(ply);
+            if alpha < draw_score && self.zobrist_stack.upcoming_repetition(board, ply) {
+                alpha = draw_score;
+                if alpha >= beta {
+                    self.tree.set_prune(Prune::Repetition);
+                    return alpha;
+                }
             }
 
             // MATE DISTANCE PRUNING
             let mate_alpha = alpha.max(i32::from(ply) - EVAL_MAX);
             let mate_beta = beta.min(EVAL_MAX - (i32::from(ply) + 1));
             if mate_alpha >= mate_beta {
+                self.tree.set_prune(Prune::MateDistance);
                 return mate_alpha;
             }
         }
@@ -389,7 +634,8 @@ impl<'a> Searcher<'a> {
         };
 
         if depth == 0 || ply >= MAX_PLY {
-            return self.qsearch(board, ply, alpha, beta);
+            // recorded as this node rather than as a child of it
+            return self.first_qsearch_node(board, ply, alpha, beta);
         }
 
         if self.is_out_of_time() {
@@ -398,15 +644,22 @@ impl<'a> Searcher<'a> {
         }
 
         self.seldepth = self.seldepth.max(ply);
+        self.stats.record(Stat::Node, depth);
+        // a child is only searched without a move when this node asks for it
+        self.stack.at_mut(ply + 1).excluded = Move::nullmove();
 
         let hash_base = ZobristHash::incremental_update_base(board);
         let hash = self.zobrist_stack.current_zobrist_hash();
 
-        let mut static_eval = evaluate(board);
+        let mut static_eval = self.evaluate(board);
         let tt_move = if let Some(entry) = self.tt.probe(hash) {
+            self.stats.record(Stat::TtHit, depth);
+            self.tree.set_tt_hit();
             let flag = entry.flag();
             let tt_score = entry.score_from_tt(ply);
-            if !is_pv && entry.cutoff_is_possible(alpha, beta, depth) {
+            if !is_pv && excluded.is_null() && entry.cutoff_is_possible(alpha, beta, depth) {
+                self.stats.record(Stat::TtCutoff, depth);
+                self.tree.set_prune(Prune::TtCutoff);
                 return tt_score;
             }
 
@@ -417,47 +670,64 @@ impl<'a> Searcher<'a> {
             entry.best_move
         } else {
             // INTERNAL ITERATIVE REDUCTION (IIR)
-            const MIN_IIR_DEPTH: Depth = 3;
-            if depth >= MIN_IIR_DEPTH {
+            if depth >= iir_min_depth() {
                 depth -= 1;
             }
 
             Move::nullmove()
         };
+        self.tree.set_static_eval(static_eval);
 
         // SYZYGY TABLEBASE PROBING
         if !IS_ROOT {
             if let Some(score) = self.tb.probe_score(board, ply) {
                 self.thread_data.increment_tb_hits();
+                self.tree.set_prune(Prune::Tablebase);
                 self.tt.store(TTFlag::EXACT, score, hash, ply, depth, Move::nullmove());
                 return score;
             }
         }
 
         // IMPROVING HEURISTIC
-        let improving = self.eval_stack.improving(static_eval, ply);
+        self.stack.at_mut(ply).static_eval = static_eval;
+        let improving = self.stack.improving(ply);
 
         let pruning_allowed = !is_pv && !in_check && alpha.abs() < MATE_THRESHOLD;
 
         let d = i32::from(depth);
         if pruning_allowed {
             // REVERSE FUTILITY PRUNING
-            const RFP_MIN_DEPTH: Depth = 8;
-            const RFP_MARGIN: EvalScore = 90;
-
             let divisor = if improving {2} else {1};
-            if depth <= RFP_MIN_DEPTH && static_eval >= (beta + (RFP_MARGIN * d / divisor)) {
+            if depth <= rfp_min_depth() && static_eval >= (beta + (rfp_margin() * d / divisor)) {
+                self.stats.record(Stat::RfpPrune, depth);
+                self.tree.set_prune(Prune::Rfp);
                 return static_eval;
             }
 
+            // RAZORING
+            if depth <= razor_max_depth() && static_eval + razor_margin() * d <= alpha {
+                // recorded as a child, so it can't overwrite this node's entry in the tree
+                self.tree.set_move(Move::nullmove());
+                self.tree.enter(ply, 0, alpha, alpha + 1, true);
+                let score = self.first_qsearch_node(board, ply, alpha, alpha + 1);
+                self.tree.exit(score);
+                if score <= alpha {
+                    self.stats.record(Stat::RazorPrune, depth);
+                    self.tree.set_prune(Prune::Razor);
+                    return score;
+                }
+            }
+
             // NULL MOVE PRUNING
-            const NMP_MIN_DEPTH: Depth = 3;
-            if DO_NULL_MOVE && depth >= NMP_MIN_DEPTH && !board.we_only_have_pawns() && static_eval >= beta {
+            if DO_NULL_MOVE && depth >= nmp_min_depth() && !board.we_only_have_pawns() && static_eval >= beta {
                 let mut reduction = 3 + depth / 3 + (3.min((static_eval - beta) / 200) as Depth);
                 reduction = reduction.min(depth);
 
                 let mut nmp_board = board.clone();
                 nmp_board.play_nullmove(&mut self.zobrist_stack);
+                self.stack.at_mut(ply).mv = Move::nullmove();
+                self.stack.at_mut(ply).moved_piece = Piece::NONE;
+                self.tree.set_move(Move::nullmove());
                 let null_move_score = -self.negamax::<false, false>(
                     &nmp_board,
                     depth - reduction,
@@ -469,9 +739,63 @@ impl<'a> Searcher<'a> {
                 self.zobrist_stack.revert_state();
 
                 if null_move_score >= beta {
+                    self.stats.record(Stat::NmpPrune, depth);
+                    self.tree.set_prune(Prune::Nmp);
                     return null_move_score;
                 }
             }
+
+            // PROBCUT
+            let probcut_beta = beta + probcut_margin();
+            if depth >= probcut_min_depth() && probcut_beta < MATE_THRESHOLD {
+                let probcut_depth = (depth - probcut_reduction()).max(1);
+                let mut generator = MoveGenerator::new();
+                while let Some(mv) =
+                    generator.next::<false>(board, &self.history, Move::nullmove(), Move::nullmove())
+                {
+                    // only captures that win enough material to clear the raised beta by themselves
+                    if !board.search_see(mv, probcut_beta - static_eval) {
+                        continue;
+                    }
+
+                    let mut next_board = board.clone();
+                    let is_legal = next_board.try_play_move(mv, &mut self.zobrist_stack, hash_base);
+                    if !is_legal {
+                        continue;
+                    }
+
+                    self.thread_data.increment_nodes();
+                    self.stack.at_mut(ply).mv = mv;
+                    self.stack.at_mut(ply).moved_piece = board.piece_on_sq(mv.from());
+                    self.tree.set_move(mv);
+
+                    // confirm with a qsearch first, as it is much cheaper than the reduced search
+                    let mut score = -self.qsearch::<false>(&next_board, ply + 1, -probcut_beta, -probcut_beta + 1);
+                    if score >= probcut_beta {
+                        self.tree.set_move(mv);
+                        score = -self.negamax::<false, true>(
+                            &next_board,
+                            probcut_depth - 1,
+                            ply + 1,
+                            -probcut_beta,
+                            -probcut_beta + 1,
+                        );
+                    }
+
+                    self.zobrist_stack.revert_state();
+
+                    if stop_flag_is_set() {
+                        return 0;
+                    }
+
+                    if score >= probcut_beta {
+                        self.stats.record(Stat::ProbCutPrune, depth);
+                        self.tree.set_prune(Prune::ProbCut);
+                        self.tt.store(TTFlag::LOWER_BOUND, score, hash, ply, probcut_depth, mv);
+                        return score;
+                    }
+                }
+            }
         }
 
         let mut generator = MoveGenerator::new();
@@ -480,23 +804,41 @@ impl<'a> Searcher<'a> {
         let mut best_score = -INF;
         let mut moves_played: i32 = 0;
         let mut quiets: ArrayVec<Move, MAX_MOVECOUNT> = ArrayVec::new();
+        if IS_ROOT {
+            self.root_scores.clear();
+        }
         while let Some(mv) =
-            generator.next::<true>(board, &self.history, self.killers.killer(ply), tt_move)
+            generator.next::<true>(board, &self.history, self.stack.at(ply).killer, tt_move)
         {
+            if mv == excluded {
+                continue;
+            }
+
             // MOVE PRUNING TECHNIQUES
-            const PRUNING_THRESHOLD: EvalScore = 700;
-            if pruning_allowed && best_score.abs() < PRUNING_THRESHOLD {
+            if pruning_allowed && best_score.abs() < pruning_threshold() {
                 // QUIET LATE MOVE PRUNING
                 let divisor = if improving {1} else {2};
                 if generator.stage() > MoveStage::KILLER && moves_played > 2 + (d * d / divisor) {
+                    self.stats.record(Stat::LmpPrune, depth);
+                    self.tree.set_prune(Prune::Lmp);
                     break;
                 }
 
+                // FUTILITY PRUNING
+                if generator.stage() > MoveStage::KILLER
+                    && mv.is_quiet()
+                    && moves_played > 0
+                    && depth <= fp_max_depth()
+                    && static_eval + fp_base() + fp_margin() * d <= alpha {
+                    self.stats.record(Stat::FutilityPrune, depth);
+                    continue;
+                }
+
                 // STATIC EXCHANGE EVALUATION (SEE) PRUNING
-                const MIN_SEE_DEPTH: Depth = 6;
                 if generator.stage() > MoveStage::KILLER
-                    && depth <= MIN_SEE_DEPTH
-                    && !board.search_see(mv, -90 * d) {
+                    && depth <= see_min_depth()
+                    && !board.search_see(mv, -see_margin() * d) {
+                    self.stats.record(Stat::SeePrune, depth);
                     continue;
                 }
             }
@@ -508,18 +850,27 @@ impl<'a> Searcher<'a> {
             }
 
             self.thread_data.increment_nodes();
+            self.stack.at_mut(ply).mv = mv;
+            self.stack.at_mut(ply).moved_piece = board.piece_on_sq(mv.from());
+            self.tree.set_move(mv);
             moves_played += 1;
 
+            if IS_ROOT && self.report_info && self.elapsed_millis() >= Self::CURRMOVE_REPORT_MIN_TIME {
+                println!("info depth {depth} currmove {} currmovenumber {moves_played}", mv.as_string());
+            }
+
             let mut score = 0;
-            if moves_played == 1 {
+            if IS_ROOT && self.options.skill.is_enabled() {
+                // every root move gets an exact score for the weakened move choice to pick from
+                score = -self.negamax::<false, true>(&next_board, depth - 1, ply + 1, -INF, INF);
+            } else if moves_played == 1 {
                 score = -self.negamax::<false, true>(&next_board, depth - 1, ply + 1, -beta, -alpha);
             } else {
                 // LATE MOVE REDUCTIONS (heavily inspired by Svart https://github.com/crippa1337/svart/blob/master/src/engine/search.rs)
-                const LMR_MIN_DEPTH: Depth = 3;
                 let lmr_threshold = if is_pv { 5 } else { 3 };
 
                 let mut do_full_depth_pvs = true;
-                if !in_check && depth >= LMR_MIN_DEPTH && moves_played > lmr_threshold {
+                if !in_check && depth >= lmr_min_depth() && moves_played > lmr_threshold {
                     let mut r = get_reduction(depth, moves_played);
                     if !is_pv {
                         r += 1;
@@ -528,8 +879,15 @@ impl<'a> Searcher<'a> {
                     if r > 1 {
                         // REDUCED PVS
                         r = r.min(depth - 1);
+                        self.stack.at_mut(ply).reduction = r;
                         score = -self.negamax::<false, true>(&next_board, depth - r, ply + 1, -alpha - 1, -alpha);
+                        self.stack.at_mut(ply).reduction = 0;
                         do_full_depth_pvs = score > alpha && score < beta; // we want to try again without reductions if we beat alpha
+
+                        self.stats.record(Stat::LmrSearch, depth);
+                        if do_full_depth_pvs {
+                            self.stats.record(Stat::LmrResearch, depth);
+                        }
                     }
                 }
 
@@ -550,6 +908,10 @@ impl<'a> Searcher<'a> {
                 return 0;
             }
 
+            if IS_ROOT {
+                self.root_scores.push((mv, score));
+            }
+
             let is_quiet = mv.is_quiet();
             if is_quiet {
                 quiets.push(mv);
@@ -565,8 +927,13 @@ impl<'a> Searcher<'a> {
                 }
 
                 if score >= beta {
+                    self.stats.record(Stat::FailHigh, depth);
+                    if moves_played == 1 {
+                        self.stats.record(Stat::FailHighFirst, depth);
+                    }
+
                     if is_quiet {
-                        self.killers.update(mv, ply);
+                        self.stack.at_mut(ply).killer = mv;
                         self.history.update(board, quiets.as_slice(), depth);
                     }
                     break;
@@ -575,20 +942,58 @@ impl<'a> Searcher<'a> {
         }
 
         if moves_played == 0 {
+            // with an excluded move, the node only tells whether the other moves are worse
+            if !excluded.is_null() {
+                return alpha;
+            }
+
             // either checkmate or stalemate
             return if in_check {
                 -EVAL_MAX + i32::from(ply)
             } else {
-                0
+                self.draw_score(ply)
             };
         }
 
-        let tt_flag = TTFlag::determine(best_score, old_alpha, alpha, beta);
-        self.tt.store(tt_flag, best_score, hash, ply, depth, best_move);
+        // the result doesn't cover every move, so it can't stand in for the position
+        if excluded.is_null() {
+            let tt_flag = TTFlag::determine(best_score, old_alpha, alpha, beta);
+            self.tt.store(tt_flag, best_score, hash, ply, depth, best_move);
+        }
         best_score
     }
 
-    fn qsearch(
+    fn qsearch<const QUIET_CHECKS: bool>(
+        &mut self,
+        board: &Board,
+        ply: Ply,
+        alpha: EvalScore,
+        beta: EvalScore,
+    ) -> EvalScore {
+        self.tree.enter(ply, 0, alpha, beta, true);
+        let score = self.qsearch_node::<QUIET_CHECKS>(board, ply, alpha, beta);
+        self.tree.exit(score);
+        score
+    }
+
+    // a qsearch in place of a main search node, with quiet checks unless they are switched off
+    fn first_qsearch_node(
+        &mut self,
+        board: &Board,
+        ply: Ply,
+        alpha: EvalScore,
+        beta: EvalScore,
+    ) -> EvalScore {
+        if self.options.no_qsearch_checks {
+            self.qsearch_node::<false>(board, ply, alpha, beta)
+        } else {
+            self.qsearch_node::<true>(board, ply, alpha, beta)
+        }
+    }
+
+    // QUIET_CHECKS is set at the first qsearch ply, where quiet moves that give check are
+    // searched as well as captures. In check, every evasion is searched instead.
+    fn qsearch_node<const QUIET_CHECKS: bool>(
         &mut self,
         board: &Board,
         ply: Ply,
@@ -601,20 +1006,40 @@ impl<'a> Searcher<'a> {
         }
 
         self.seldepth = self.seldepth.max(ply);
-
-        let stand_pat = evaluate(board);
-        if stand_pat >= beta {
-            return stand_pat;
+        self.stats.record(Stat::QsearchNode, 0);
+
+        let draw_score = self.draw_score(ply);
+        if alpha < draw_score && self.zobrist_stack.upcoming_repetition(board, ply) {
+            alpha = draw_score;
+            if alpha >= beta {
+                self.tree.set_prune(Prune::Repetition);
+                return alpha;
+            }
         }
 
-        if stand_pat > alpha {
-            alpha = stand_pat;
+        // there is no standing pat when in check, as every evasion might lose
+        let in_check = board.in_check();
+        let stand_pat = if in_check { -INF } else { self.evaluate(board) };
+        // the frame's static eval is left alone: razoring runs the qsearch on a frame that
+        // negamax has already filled, and no negamax node below a qsearch ever reads it
+        if !in_check {
+            self.tree.set_static_eval(stand_pat);
+            if stand_pat >= beta {
+                self.tree.set_prune(Prune::StandPat);
+                return stand_pat;
+            }
+
+            if stand_pat > alpha {
+                alpha = stand_pat;
+            }
         }
 
         let hash_base = ZobristHash::incremental_update_base(board);
         let hash = self.zobrist_stack.current_zobrist_hash();
         if let Some(entry) = self.tt.probe(hash) {
+            self.tree.set_tt_hit();
             if entry.cutoff_is_possible(alpha, beta, 0) {
+                self.tree.set_prune(Prune::TtCutoff);
                 return entry.score_from_tt(ply);
             }
         }
@@ -624,9 +1049,38 @@ impl<'a> Searcher<'a> {
         let old_alpha = alpha;
         let mut best_score = stand_pat;
         let mut best_move = Move::nullmove();
-        while let Some(mv) =
-            generator.next::<false>(board, &self.history, Move::nullmove(), Move::nullmove())
-        {
+        let mut moves_played = 0;
+        let full_movegen = in_check || QUIET_CHECKS;
+        loop {
+            let next = if full_movegen {
+                generator.next::<true>(board, &self.history, Move::nullmove(), Move::nullmove())
+            } else {
+                generator.next::<false>(board, &self.history, Move::nullmove(), Move::nullmove())
+            };
+            let Some(mv) = next else {
+                break;
+            };
+
+            // past the good captures, only quiet checks that don't hang the piece are left
+            let quiet_check = !in_check && generator.stage() > MoveStage::CAPTURE;
+            if quiet_check && (!mv.is_quiet() || !board.gives_check(mv) || !board.search_see(mv, 0))
+            {
+                continue;
+            }
+
+            // DELTA PRUNING
+            if !in_check && mv.is_capture() && !mv.is_promo() {
+                let victim = if mv.flag() == Flag::EP {
+                    Piece::PAWN
+                } else {
+                    board.piece_on_sq(mv.to())
+                };
+
+                if stand_pat + SEE_VALS[victim.as_index()] + delta_margin() <= alpha {
+                    continue;
+                }
+            }
+
             let mut next_board = board.clone();
             let is_legal = next_board.try_play_move(mv, &mut self.zobrist_stack, hash_base);
             if !is_legal {
@@ -634,8 +1088,12 @@ impl<'a> Searcher<'a> {
             }
 
             self.thread_data.increment_nodes();
+            self.stack.at_mut(ply).mv = mv;
+            self.stack.at_mut(ply).moved_piece = board.piece_on_sq(mv.from());
+            self.tree.set_move(mv);
+            moves_played += 1;
 
-            let score = -self.qsearch(&next_board, ply + 1, -beta, -alpha);
+            let score = -self.qsearch::<false>(&next_board, ply + 1, -beta, -alpha);
 
             self.zobrist_stack.revert_state();
 
@@ -657,6 +1115,10 @@ impl<'a> Searcher<'a> {
             }
         }
 
+        if in_check && moves_played == 0 {
+            return -EVAL_MAX + i32::from(ply);
+        }
+
         let flag = TTFlag::determine(best_score, old_alpha, alpha, beta);
         self.tt.store(flag, best_score, hash, ply, 0, best_move);
         best_score
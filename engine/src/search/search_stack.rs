@@ -0,0 +1,68 @@
+use crate::{
+    board::{board_representation::Piece, chess_move::Move},
+    eval::evaluation::{EvalScore, INF},
+};
+
+use super::search::{Depth, Ply};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Frame {
+    pub static_eval: EvalScore,
+    // the move played from this ply to reach the next one, null for a null move
+    pub mv: Move,
+    pub moved_piece: Piece,
+    pub excluded: Move,
+    pub killer: Move,
+    pub double_extensions: u8,
+    pub reduction: Depth,
+}
+
+impl Frame {
+    const fn new() -> Self {
+        Self {
+            static_eval: INF,
+            mv: Move::nullmove(),
+            moved_piece: Piece::NONE,
+            excluded: Move::nullmove(),
+            killer: Move::nullmove(),
+            double_extensions: 0,
+            reduction: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SearchStack {
+    frames: [Frame; Self::SIZE],
+}
+
+impl SearchStack {
+    // empty frames before the root, so heuristics can look back without checking the ply
+    const OFFSET: usize = 2;
+    // qsearch can run past MAX_PLY, so every ply gets a frame
+    const SIZE: usize = Ply::MAX as usize + 1 + Self::OFFSET;
+
+    pub const fn new() -> Self {
+        Self {
+            frames: [Frame::new(); Self::SIZE],
+        }
+    }
+
+    pub const fn at(&self, ply: Ply) -> &Frame {
+        &self.frames[ply as usize + Self::OFFSET]
+    }
+
+    pub const fn at_mut(&mut self, ply: Ply) -> &mut Frame {
+        &mut self.frames[ply as usize + Self::OFFSET]
+    }
+
+    // the frame `back` plies before this one, up to OFFSET plies before the root
+    pub const fn back(&self, ply: Ply, back: usize) -> &Frame {
+        &self.frames[ply as usize + Self::OFFSET - back]
+    }
+
+    // compares against our own static eval from two plies ago
+    pub const fn improving(&self, ply: Ply) -> bool {
+        self.at(ply).static_eval > self.back(ply, 2).static_eval
+    }
+}
@@ -0,0 +1,166 @@
+use super::{search::Depth, thread_data::Nodes};
+
+// Per-depth counters for measuring how pruning changes affect the tree. Without the
+// "search-stats" feature SearchStats is empty and every method compiles to nothing.
+#[derive(Debug, Copy, Clone)]
+pub enum Stat {
+    Node,
+    QsearchNode,
+    FailHigh,
+    FailHighFirst,
+    TtHit,
+    TtCutoff,
+    RfpPrune,
+    NmpPrune,
+    LmpPrune,
+    SeePrune,
+    RazorPrune,
+    ProbCutPrune,
+    FutilityPrune,
+    LmrSearch,
+    LmrResearch,
+}
+
+#[cfg(feature = "search-stats")]
+const NUM_STATS: usize = Stat::LmrResearch as usize + 1;
+#[cfg(feature = "search-stats")]
+const NUM_DEPTHS: usize = Depth::MAX as usize + 1;
+
+#[cfg(feature = "search-stats")]
+#[derive(Debug, Clone)]
+pub struct SearchStats {
+    counters: Vec<[u64; NUM_STATS]>,
+    iteration_nodes: Vec<Nodes>,
+}
+
+#[cfg(not(feature = "search-stats"))]
+#[derive(Debug, Clone)]
+pub struct SearchStats;
+
+#[cfg(not(feature = "search-stats"))]
+impl SearchStats {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    pub const fn record(&mut self, _stat: Stat, _depth: Depth) {}
+
+    pub const fn end_iteration(&mut self, _total_nodes: Nodes) {}
+
+    pub const fn merge(&mut self, _other: &Self) {}
+
+    pub const fn print(&self, _prefix: &str) {}
+}
+
+#[cfg(feature = "search-stats")]
+#[allow(clippy::cast_precision_loss, clippy::cast_sign_loss)]
+impl SearchStats {
+    pub fn new() -> Self {
+        Self {
+            counters: vec![[0; NUM_STATS]; NUM_DEPTHS],
+            iteration_nodes: vec![],
+        }
+    }
+
+    pub fn record(&mut self, stat: Stat, depth: Depth) {
+        self.counters[depth.max(0) as usize][stat as usize] += 1;
+    }
+
+    // total_nodes is the node count after each completed iteration
+    pub fn end_iteration(&mut self, total_nodes: Nodes) {
+        self.iteration_nodes.push(total_nodes);
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        for (row, other_row) in self.counters.iter_mut().zip(&other.counters) {
+            for (count, other_count) in row.iter_mut().zip(other_row) {
+                *count += other_count;
+            }
+        }
+        self.iteration_nodes.extend(&other.iteration_nodes);
+    }
+
+    fn total(&self, stat: Stat) -> u64 {
+        self.counters.iter().map(|row| row[stat as usize]).sum()
+    }
+
+    // geometric mean of the node growth between iterations, over every search merged in
+    fn branching_factor(&self) -> Option<f64> {
+        let mut log_sum = 0.0;
+        let mut count = 0;
+        let mut prev_iteration = 0;
+        let mut prev_total = 0;
+        for &total in &self.iteration_nodes {
+            if total < prev_total {
+                // a new search was merged in
+                prev_iteration = 0;
+                prev_total = 0;
+            }
+
+            let iteration = total - prev_total;
+            if prev_iteration > 0 && iteration > 0 {
+                log_sum += (iteration as f64 / prev_iteration as f64).ln();
+                count += 1;
+            }
+            prev_iteration = iteration;
+            prev_total = total;
+        }
+
+        (count > 0).then(|| (log_sum / f64::from(count)).exp())
+    }
+
+    pub fn print(&self, prefix: &str) {
+        let percent = |part: u64, whole: u64| 100.0 * part as f64 / whole.max(1) as f64;
+
+        println!(
+            "{prefix}{:>5} {:>10} {:>6} {:>6} {:>6} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>6}",
+            "depth",
+            "nodes",
+            "fh1%",
+            "tthit%",
+            "ttcut%",
+            "rfp",
+            "razor",
+            "probcut",
+            "nmp",
+            "lmp",
+            "fp",
+            "see",
+            "lmr",
+            "lmrre%"
+        );
+        for (depth, row) in self.counters.iter().enumerate().skip(1) {
+            let stat = |s: Stat| row[s as usize];
+            let nodes = stat(Stat::Node);
+            if nodes == 0 {
+                continue;
+            }
+
+            println!(
+                "{prefix}{depth:>5} {nodes:>10} {:>6.1} {:>6.1} {:>6.1} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>6.1}",
+                percent(stat(Stat::FailHighFirst), stat(Stat::FailHigh)),
+                percent(stat(Stat::TtHit), nodes),
+                percent(stat(Stat::TtCutoff), nodes),
+                stat(Stat::RfpPrune),
+                stat(Stat::RazorPrune),
+                stat(Stat::ProbCutPrune),
+                stat(Stat::NmpPrune),
+                stat(Stat::LmpPrune),
+                stat(Stat::FutilityPrune),
+                stat(Stat::SeePrune),
+                stat(Stat::LmrSearch),
+                percent(stat(Stat::LmrResearch), stat(Stat::LmrSearch)),
+            );
+        }
+
+        let nodes = self.total(Stat::Node);
+        let qsearch_nodes = self.total(Stat::QsearchNode);
+        println!(
+            "{prefix}fail high first {:.1}%, qsearch nodes {:.1}%, branching factor {}",
+            percent(self.total(Stat::FailHighFirst), self.total(Stat::FailHigh)),
+            percent(qsearch_nodes, nodes + qsearch_nodes),
+            self.branching_factor()
+                .map_or_else(|| "n/a".to_owned(), |bf| format!("{bf:.2}"))
+        );
+    }
+}
@@ -0,0 +1,475 @@
+#[cfg(feature = "search-tree")]
+use std::io::{BufWriter, Write};
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+};
+
+use crate::{board::chess_move::Move, eval::evaluation::EvalScore};
+
+use super::search::{Depth, Ply};
+
+pub const TREE_ENABLED: bool = cfg!(feature = "search-tree");
+
+// Why a node returned without searching all of its moves
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Prune {
+    None,
+    Draw,
+    Repetition,
+    MateDistance,
+    TtCutoff,
+    Tablebase,
+    Rfp,
+    Razor,
+    ProbCut,
+    Nmp,
+    Lmp,
+    StandPat,
+}
+
+impl Prune {
+    const ALL: [Self; 12] = [
+        Self::None,
+        Self::Draw,
+        Self::Repetition,
+        Self::MateDistance,
+        Self::TtCutoff,
+        Self::Tablebase,
+        Self::Rfp,
+        Self::Razor,
+        Self::ProbCut,
+        Self::Nmp,
+        Self::Lmp,
+        Self::StandPat,
+    ];
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "-",
+            Self::Draw => "draw",
+            Self::Repetition => "upcoming-rep",
+            Self::MateDistance => "mdp",
+            Self::TtCutoff => "tt-cutoff",
+            Self::Tablebase => "tb",
+            Self::Rfp => "rfp",
+            Self::Razor => "razor",
+            Self::ProbCut => "probcut",
+            Self::Nmp => "nmp",
+            Self::Lmp => "lmp",
+            Self::StandPat => "stand-pat",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TreeNode {
+    pub parent: u32,
+    pub mv: Move,
+    pub ply: Ply,
+    pub depth: Depth,
+    pub alpha: EvalScore,
+    pub beta: EvalScore,
+    pub static_eval: EvalScore,
+    pub score: EvalScore,
+    pub tt_hit: bool,
+    pub qsearch: bool,
+    pub prune: Prune,
+}
+
+impl TreeNode {
+    const NO_PARENT: u32 = u32::MAX;
+    const SIZE: usize = 28;
+
+    #[cfg(feature = "search-tree")]
+    fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[0..4].copy_from_slice(&self.parent.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.mv.as_u16().to_le_bytes());
+        bytes[6] = self.ply;
+        bytes[7] = self.depth.to_le_bytes()[0];
+        bytes[8..12].copy_from_slice(&self.alpha.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.beta.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.static_eval.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.score.to_le_bytes());
+        bytes[24] = u8::from(self.tt_hit) | (u8::from(self.qsearch) << 1);
+        bytes[25] = self.prune as u8;
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        let i32_at = |i: usize| i32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        Some(Self {
+            parent: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            mv: Move::from_u16(u16::from_le_bytes([bytes[4], bytes[5]])),
+            ply: bytes[6],
+            depth: Depth::from_le_bytes([bytes[7]]),
+            alpha: i32_at(8),
+            beta: i32_at(12),
+            static_eval: i32_at(16),
+            score: i32_at(20),
+            tt_hit: bytes[24] & 1 != 0,
+            qsearch: bytes[24] & 2 != 0,
+            prune: *Prune::ALL.get(usize::from(bytes[25]))?,
+        })
+    }
+}
+
+// Records the search tree, in the order nodes are entered, up to a ply and a node budget for
+// each iteration.
+// Without the "search-tree" feature the recorder is empty and compiles to nothing.
+#[cfg(feature = "search-tree")]
+#[derive(Debug, Clone)]
+pub struct SearchTree {
+    nodes: Vec<TreeNode>,
+    // one entry per node on the current path, None for the ones outside the budget
+    path: Vec<Option<u32>>,
+    next_move: Move,
+    max_ply: Ply,
+    max_nodes: usize,
+    // where the current iteration's nodes begin
+    iteration_start: usize,
+}
+
+#[cfg(not(feature = "search-tree"))]
+#[derive(Debug, Clone)]
+pub struct SearchTree;
+
+#[cfg(not(feature = "search-tree"))]
+impl SearchTree {
+    pub const fn new(_max_ply: Ply, _max_nodes: usize) -> Self {
+        Self
+    }
+
+    pub const fn start_iteration(&mut self) {}
+
+    pub const fn set_move(&mut self, _mv: Move) {}
+
+    pub const fn enter(
+        &mut self,
+        _ply: Ply,
+        _depth: Depth,
+        _alpha: EvalScore,
+        _beta: EvalScore,
+        _qsearch: bool,
+    ) {
+    }
+
+    pub const fn set_static_eval(&mut self, _static_eval: EvalScore) {}
+
+    pub const fn set_tt_hit(&mut self) {}
+
+    pub const fn set_prune(&mut self, _prune: Prune) {}
+
+    pub const fn exit(&mut self, _score: EvalScore) {}
+
+    /// # Errors
+    ///
+    /// Never fails, as there is nothing to write without the "search-tree" feature.
+    pub const fn save(&self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "search-tree")]
+impl SearchTree {
+    pub const fn new(max_ply: Ply, max_nodes: usize) -> Self {
+        Self {
+            nodes: vec![],
+            path: vec![],
+            next_move: Move::nullmove(),
+            max_ply,
+            max_nodes,
+            iteration_start: 0,
+        }
+    }
+
+    // each iteration gets the whole node budget, so the deepest ones are recorded too
+    pub const fn start_iteration(&mut self) {
+        self.iteration_start = self.nodes.len();
+    }
+
+    // the move leading to the next node entered
+    pub const fn set_move(&mut self, mv: Move) {
+        self.next_move = mv;
+    }
+
+    pub fn enter(
+        &mut self,
+        ply: Ply,
+        depth: Depth,
+        alpha: EvalScore,
+        beta: EvalScore,
+        qsearch: bool,
+    ) {
+        let parent = self.path.last().copied().flatten();
+        let parent_recorded = ply == 0 || parent.is_some();
+        let iteration_nodes = self.nodes.len() - self.iteration_start;
+        if !parent_recorded || ply > self.max_ply || iteration_nodes >= self.max_nodes {
+            self.path.push(None);
+            return;
+        }
+
+        self.path.push(Some(self.nodes.len() as u32));
+        self.nodes.push(TreeNode {
+            parent: parent.unwrap_or(TreeNode::NO_PARENT),
+            mv: if ply == 0 {
+                Move::nullmove()
+            } else {
+                self.next_move
+            },
+            ply,
+            depth,
+            alpha,
+            beta,
+            static_eval: 0,
+            score: 0,
+            tt_hit: false,
+            qsearch,
+            prune: Prune::None,
+        });
+    }
+
+    fn current(&mut self) -> Option<&mut TreeNode> {
+        let index = (*self.path.last()?)?;
+        self.nodes.get_mut(index as usize)
+    }
+
+    pub fn set_static_eval(&mut self, static_eval: EvalScore) {
+        if let Some(node) = self.current() {
+            node.static_eval = static_eval;
+        }
+    }
+
+    pub fn set_tt_hit(&mut self) {
+        if let Some(node) = self.current() {
+            node.tt_hit = true;
+        }
+    }
+
+    pub fn set_prune(&mut self, prune: Prune) {
+        if let Some(node) = self.current() {
+            node.prune = prune;
+        }
+    }
+
+    pub fn exit(&mut self, score: EvalScore) {
+        if let Some(node) = self.current() {
+            node.score = score;
+        }
+        self.path.pop();
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(&FILE_MAGIC)?;
+        file.write_all(&FILE_VERSION.to_le_bytes())?;
+        file.write_all(&(self.nodes.len() as u64).to_le_bytes())?;
+        for node in &self.nodes {
+            file.write_all(&node.to_bytes())?;
+        }
+
+        file.flush()
+    }
+}
+
+const FILE_MAGIC: [u8; 8] = *b"WAHOOTRE";
+const FILE_VERSION: u32 = 1;
+
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a search tree file.
+pub fn load(path: &str) -> io::Result<Vec<TreeNode>> {
+    fn invalid(msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    let mut file = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)?;
+    if magic != FILE_MAGIC {
+        return Err(invalid("not a Wahoo search tree file"));
+    }
+
+    let mut version = [0; 4];
+    file.read_exact(&mut version)?;
+    if u32::from_le_bytes(version) != FILE_VERSION {
+        return Err(invalid("unsupported search tree file version"));
+    }
+
+    let mut count = [0; 8];
+    file.read_exact(&mut count)?;
+
+    let mut nodes = vec![];
+    let mut bytes = [0; TreeNode::SIZE];
+    for _ in 0..u64::from_le_bytes(count) {
+        file.read_exact(&mut bytes)?;
+        nodes.push(TreeNode::from_bytes(&bytes).ok_or_else(|| invalid("corrupt node"))?);
+    }
+
+    Ok(nodes)
+}
+
+// number of recorded nodes in each subtree, which works as nodes are stored parents first
+fn subtree_sizes(nodes: &[TreeNode]) -> Vec<u64> {
+    let mut sizes = vec![1; nodes.len()];
+    for i in (0..nodes.len()).rev() {
+        let parent = nodes[i].parent;
+        if parent != TreeNode::NO_PARENT {
+            sizes[parent as usize] += sizes[i];
+        }
+    }
+    sizes
+}
+
+fn print_nodes(nodes: &[TreeNode], sizes: &[u64], indices: &[usize]) {
+    println!(
+        "{:>8} {:>6} {:>10} {:>5} {:>7} {:>7} {:>7} {:>3} {:>12} {:>7}",
+        "index", "move", "nodes", "depth", "alpha", "beta", "eval", "tt", "pruned", "score"
+    );
+    for &i in indices {
+        let node = nodes[i];
+        let mv = if node.mv.is_null() {
+            "-".to_owned()
+        } else {
+            node.mv.as_string()
+        };
+        let depth = if node.qsearch {
+            "q".to_owned()
+        } else {
+            node.depth.to_string()
+        };
+
+        println!(
+            "{i:>8} {mv:>6} {:>10} {depth:>5} {:>7} {:>7} {:>7} {:>3} {:>12} {:>7}",
+            sizes[i],
+            node.alpha,
+            node.beta,
+            node.static_eval,
+            if node.tt_hit { "hit" } else { "-" },
+            node.prune.as_str(),
+            node.score
+        );
+    }
+}
+
+// usage: tree <file>                       lists the root searches
+//        tree <file> <index> [moves...]     shows the children of the node reached by
+//                                           following the moves from the given node,
+//                                           sorted by nodes
+pub fn query(args: &[String]) {
+    let Some(path) = args.first() else {
+        println!("expected search tree file to read!");
+        return;
+    };
+
+    let nodes = match load(path) {
+        Ok(nodes) => nodes,
+        Err(e) => {
+            println!("failed to load search tree: {e}");
+            return;
+        }
+    };
+    let sizes = subtree_sizes(&nodes);
+
+    let Some(start) = args.get(1) else {
+        let roots = (0..nodes.len())
+            .filter(|&i| nodes[i].parent == TreeNode::NO_PARENT)
+            .collect::<Vec<_>>();
+        print_nodes(&nodes, &sizes, &roots);
+        return;
+    };
+
+    let Some(mut current) = start.parse::<usize>().ok().filter(|&i| i < nodes.len()) else {
+        println!("no node {start}");
+        return;
+    };
+
+    let children_of = |parent: usize| {
+        (parent + 1..nodes.len())
+            .filter(|&i| nodes[i].parent as usize == parent)
+            .collect::<Vec<_>>()
+    };
+
+    // a move can be searched several times (reductions, re-searches), so take the last
+    for mv_str in &args[2..] {
+        let Some(next) = children_of(current)
+            .into_iter()
+            .rev()
+            .find(|&i| nodes[i].mv.as_string() == *mv_str)
+        else {
+            println!("no child {mv_str} of node {current}");
+            return;
+        };
+        current = next;
+    }
+
+    print_nodes(&nodes, &sizes, &[current]);
+    println!();
+
+    let mut children = children_of(current);
+    children.sort_by_key(|&i| std::cmp::Reverse(sizes[i]));
+    print_nodes(&nodes, &sizes, &children);
+}
+
+#[cfg(all(test, feature = "search-tree"))]
+mod tests {
+    use super::{load, subtree_sizes, Prune, SearchTree};
+    use crate::board::{board_representation::Board, chess_move::Move};
+
+    #[test]
+    fn tree_round_trips() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let mut tree = SearchTree::new(1, 100);
+
+        tree.enter(0, 2, -100, 100, false);
+        tree.set_static_eval(20);
+        for mv in ["e2e4", "d2d4"] {
+            tree.set_move(Move::from_string(mv, &board));
+            tree.enter(1, 1, -100, 100, false);
+            tree.set_prune(Prune::Rfp);
+            // beyond the ply limit, so not recorded
+            tree.enter(2, 0, -100, 100, true);
+            tree.exit(5);
+            tree.exit(-30);
+        }
+        tree.exit(30);
+
+        let path = std::env::temp_dir().join("wahoo_tree_test.bin");
+        let path = path.to_str().unwrap();
+        tree.save(path).unwrap();
+        let nodes = load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(subtree_sizes(&nodes), vec![3, 1, 1]);
+        assert_eq!(nodes[0].score, 30);
+        assert_eq!(nodes[2].mv.as_string(), "d2d4");
+        assert_eq!(nodes[2].prune, Prune::Rfp);
+    }
+
+    #[test]
+    fn each_iteration_gets_the_node_budget() {
+        let mut tree = SearchTree::new(1, 2);
+        for depth in 1..=3 {
+            tree.start_iteration();
+            tree.enter(0, depth, -100, 100, false);
+            for _ in 0..3 {
+                tree.set_move(Move::nullmove());
+                tree.enter(1, depth - 1, -100, 100, false);
+                tree.exit(0);
+            }
+            tree.exit(0);
+        }
+
+        // the root and one child of each iteration
+        assert_eq!(tree.nodes.len(), 6);
+        assert_eq!(tree.nodes[4].depth, 3);
+    }
+}
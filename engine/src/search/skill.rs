@@ -0,0 +1,170 @@
+use crate::{board::chess_move::Move, eval::evaluation::EvalScore};
+
+use super::thread_data::Nodes;
+
+// Strength limiting, loosely following Stockfish's skill levels. A weakened search combines
+// a node limit, noise on the static eval and a weighted random choice between the best
+// few root moves. Level 20 is full strength.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self::from_level(Self::MAX_LEVEL)
+    }
+}
+
+impl Skill {
+    pub const MAX_LEVEL: u8 = 20;
+
+    // the node limit of each level below full strength
+    const NODE_LIMITS: [Nodes; Self::MAX_LEVEL as usize] = [
+        256, 384, 576, 864, 1296, 1944, 2916, 4374, 6561, 9841, 14762, 22143, 33215, 49822, 74733,
+        112_100, 168_151, 252_226, 378_340, 567_510,
+    ];
+
+    // Levels are rated along a line fitted to one measurement per level, taken with the
+    // skill_match tool in the tuning crate. Full strength searches of 64 to 262144 nodes were
+    // rated in a ladder of matches against twice their nodes, then each level from 5 up was
+    // played against the closest rung, and levels 0 to 4, weaker than any full strength search,
+    // against the level above. Every match was 40 games (20 random 8 ply openings, each with
+    // both colors), good for about +-100 Elo. The least squares line has a residual spread of
+    // 48 Elo, which puts each level within +-21 (middle levels) to +-41 (ends) of it at 95%.
+    // That leaves out the ladder's own error, which stretches or shrinks the scale as a whole.
+    // The scale puts full strength at 262144 nodes at 2900.
+    const ELO_AT_LEVEL_0_TENTHS: i32 = 3044;
+    const ELO_PER_LEVEL_TENTHS: i32 = 1116;
+
+    const fn level_elo(level: u8) -> i32 {
+        (Self::ELO_AT_LEVEL_0_TENTHS + Self::ELO_PER_LEVEL_TENTHS * level as i32) / 10
+    }
+
+    // UCI_Elo range, from the weakest level to the strongest below full strength
+    pub const MIN_ELO: i32 = Self::level_elo(0);
+    pub const MAX_ELO: i32 = Self::level_elo(Self::MAX_LEVEL - 1);
+
+    const CANDIDATE_MOVES: usize = 4;
+    const PAWN_VALUE: EvalScore = 100;
+
+    pub fn from_level(level: u8) -> Self {
+        Self {
+            level: level.min(Self::MAX_LEVEL),
+        }
+    }
+
+    // the strongest level that is no stronger than asked for
+    pub fn from_elo(elo: i32) -> Self {
+        let level = (0..Self::MAX_LEVEL)
+            .rev()
+            .find(|&level| Self::level_elo(level) <= elo)
+            .unwrap_or(0);
+        Self::from_level(level)
+    }
+
+    pub const fn is_enabled(self) -> bool {
+        self.level < Self::MAX_LEVEL
+    }
+
+    pub fn node_limit(self) -> Nodes {
+        Self::NODE_LIMITS[usize::from(self.level)]
+    }
+
+    pub fn eval_noise(self, hash: u64, seed: u64) -> EvalScore {
+        const NOISE_PER_LEVEL: u64 = 5;
+
+        let amplitude = u64::from(Self::MAX_LEVEL - self.level) * NOISE_PER_LEVEL;
+        if amplitude == 0 {
+            return 0;
+        }
+
+        // the same position gets the same noise for the whole search, which keeps the TT sane
+        let r = splitmix64(hash ^ seed) % (2 * amplitude + 1);
+        r as EvalScore - amplitude as EvalScore
+    }
+
+    // scored_moves holds an exact score for every root move
+    #[allow(clippy::cast_sign_loss)]
+    pub fn pick_move(self, scored_moves: &[(Move, EvalScore)], seed: u64) -> Move {
+        let mut candidates = scored_moves.to_vec();
+        candidates.sort_by_key(|&(_, score)| -score);
+        candidates.truncate(Self::CANDIDATE_MOVES);
+
+        let top = candidates[0].1;
+        let delta = (top - candidates[candidates.len() - 1].1).min(Self::PAWN_VALUE);
+        let weakness = 120 - 2 * i32::from(self.level);
+
+        let mut rng = seed;
+        let mut best = candidates[0];
+        let mut max_score = EvalScore::MIN;
+        for &(mv, score) in &candidates {
+            rng = splitmix64(rng);
+            let random = (rng % weakness as u64) as EvalScore;
+            let push = (weakness * (top - score) + delta * random) / 128;
+            if score + push >= max_score {
+                max_score = score + push;
+                best = (mv, score);
+            }
+        }
+
+        best.0
+    }
+}
+
+pub const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Skill;
+    use crate::board::{
+        board_representation::{Board, START_FEN},
+        chess_move::Move,
+    };
+
+    #[test]
+    fn elo_maps_onto_levels() {
+        assert_eq!(Skill::from_elo(0), Skill::from_level(0));
+        assert_eq!(Skill::from_elo(Skill::MIN_ELO), Skill::from_level(0));
+        assert_eq!(Skill::from_elo(Skill::MAX_ELO), Skill::from_level(19));
+        assert!(Skill::from_elo(Skill::MAX_ELO).is_enabled());
+        assert!(!Skill::default().is_enabled());
+
+        assert!(Skill::NODE_LIMITS.windows(2).all(|pair| pair[0] < pair[1]));
+        for level in 0..Skill::MAX_LEVEL {
+            let elo = Skill::level_elo(level);
+            assert_eq!(Skill::from_elo(elo), Skill::from_level(level));
+            assert_eq!(
+                Skill::from_elo(elo - 1),
+                Skill::from_level(level.saturating_sub(1))
+            );
+        }
+    }
+
+    #[test]
+    fn weak_levels_sometimes_miss_the_best_move() {
+        let board = Board::from_fen(START_FEN);
+        let scored_moves = [
+            (Move::from_string("e2e4", &board), 30),
+            (Move::from_string("d2d4", &board), 25),
+            (Move::from_string("g1f3", &board), 20),
+            (Move::from_string("a2a3", &board), -40),
+        ];
+
+        let best_move_count = |skill: Skill| {
+            (0..1000)
+                .filter(|&seed| skill.pick_move(&scored_moves, seed) == scored_moves[0].0)
+                .count()
+        };
+
+        let weak = best_move_count(Skill::from_level(0));
+        let strong = best_move_count(Skill::from_level(19));
+        assert!(weak < 1000);
+        assert!(strong > weak);
+    }
+}
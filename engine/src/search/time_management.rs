@@ -1,6 +1,17 @@
 use std::time::Instant;
 
-use crate::board::board_representation::{Color, NUM_COLORS};
+use crate::{
+    board::board_representation::{Color, NUM_COLORS},
+    eval::evaluation::EvalScore,
+};
+
+use super::thread_data::Nodes;
+use super::tunables::{
+    tm_asp_scale, tm_base_divisor, tm_emergency_divisor, tm_emergency_inc_percent,
+    tm_emergency_time, tm_inc_percent, tm_max_percent, tm_mtg_reserve, tm_node_base, tm_node_scale,
+    tm_score_drop_max, tm_score_drop_scale, tm_soft_percent, tm_stability_base, tm_stability_max,
+    tm_stability_step,
+};
 
 pub type Milliseconds = u128;
 
@@ -12,14 +23,74 @@ pub struct TimeArgs {
     pub moves_to_go: u64,
 }
 
+// a side's clock in nodes, for nodestime
+#[derive(Debug, Copy, Clone)]
+struct NodeClock {
+    available: u128,
+    // what each period of a repeating time control adds
+    period: u128,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct TimeManager {
     pub overhead: Milliseconds,
+    nodes_time: Nodes,
+    // our own clocks in nodes when nodestime is set, since the GUI's clock runs on real time
+    node_clocks: [Option<NodeClock>; NUM_COLORS as usize],
 }
 
 impl TimeManager {
     pub const fn new(overhead: Milliseconds) -> Self {
-        Self { overhead }
+        Self {
+            overhead,
+            nodes_time: 0,
+            node_clocks: [None; NUM_COLORS as usize],
+        }
+    }
+
+    // nodes per millisecond, or 0 to measure real time
+    pub const fn set_nodes_time(&mut self, nodes_time: Nodes) {
+        self.nodes_time = nodes_time;
+        self.node_clocks = [None; NUM_COLORS as usize];
+    }
+
+    pub const fn new_game(&mut self) {
+        self.node_clocks = [None; NUM_COLORS as usize];
+    }
+
+    // With nodestime, replaces our clock by the node clock (converted back to milliseconds).
+    // Each side's starts from the GUI's clock on its first search of a game, which is also
+    // taken as the time of a whole period.
+    pub fn node_clock(&mut self, mut args: TimeArgs, color: Color) -> TimeArgs {
+        let c = color.as_index();
+        if self.nodes_time == 0 || args.time[c] == 0 {
+            return args;
+        }
+
+        let nodes_time = u128::from(self.nodes_time);
+        let clock = self.node_clocks[c].get_or_insert(NodeClock {
+            available: args.time[c] * nodes_time,
+            period: args.time[c] * nodes_time,
+        });
+        args.time[c] = clock.available / nodes_time;
+        args
+    }
+
+    // only searches on the clock use it up, not depth, node or movetime searches
+    pub fn spend_nodes(&mut self, args: TimeArgs, color: Color, nodes: Nodes) {
+        let c = color.as_index();
+        if args.time[c] == 0 || args.move_time > 0 {
+            return;
+        }
+
+        if let Some(clock) = &mut self.node_clocks[c] {
+            let mut gained = args.inc[c] * u128::from(self.nodes_time);
+            // the last move of a period brings in the next one
+            if args.moves_to_go == 1 {
+                gained += clock.period;
+            }
+            clock.available = (clock.available + gained).saturating_sub(u128::from(nodes));
+        }
     }
 
     pub fn calculate_search_time(self, args: TimeArgs, color: Color) -> Milliseconds {
@@ -27,17 +98,60 @@ impl TimeManager {
             return args.move_time.saturating_sub(self.overhead);
         }
 
-        let time = args.time[color.as_index()];
+        let available = args.time[color.as_index()].saturating_sub(self.overhead);
         let inc = args.inc[color.as_index()];
 
-        let normal_time = (time / 15 + (4 * inc / 5)).saturating_sub(self.overhead);
-        let to_go_time = if args.moves_to_go > 0 {
-            (time / u128::from(args.moves_to_go)).saturating_sub(self.overhead)
+        let search_time = if available < tm_emergency_time() {
+            // very low on the clock: play fast and live off the increment
+            available / tm_emergency_divisor() + inc * tm_emergency_inc_percent() / 100
+        } else if args.moves_to_go > 0 {
+            // repeating time controls: spread the clock over the moves left in this period,
+            // keeping a few moves in reserve so the last move before the control isn't rushed
+            let moves = u128::from(args.moves_to_go).min(tm_base_divisor());
+            available / (moves + tm_mtg_reserve()) + inc * tm_inc_percent() / 100
         } else {
-            0
+            available / tm_base_divisor() + inc * tm_inc_percent() / 100
         };
 
-        normal_time.max(to_go_time)
+        // no single search may use more than this, which is what keeps us from flagging
+        search_time.min(available * tm_max_percent() / 100)
+    }
+
+    // Plays out a game of the given length on the clock, assuming every search runs to its
+    // hard limit and the GUI adds latency on top. Returns the lowest clock reached, or None
+    // if the flag fell.
+    pub fn simulate_clock(
+        self,
+        start: TimeArgs,
+        color: Color,
+        moves: usize,
+        latency: Milliseconds,
+    ) -> Option<Milliseconds> {
+        let period_time = start.time[color.as_index()];
+        let period_moves = start.moves_to_go;
+
+        let mut args = start;
+        let mut lowest_clock = period_time;
+        for _ in 0..moves {
+            let clock = args.time[color.as_index()];
+            let spent = self.calculate_search_time(args, color) + latency;
+            if spent >= clock {
+                return None;
+            }
+
+            lowest_clock = lowest_clock.min(clock - spent);
+            args.time[color.as_index()] = clock - spent + args.inc[color.as_index()];
+
+            if period_moves > 0 {
+                args.moves_to_go -= 1;
+                if args.moves_to_go == 0 {
+                    args.moves_to_go = period_moves;
+                    args.time[color.as_index()] += period_time;
+                }
+            }
+        }
+
+        Some(lowest_clock)
     }
 }
 
@@ -46,34 +160,169 @@ pub struct SearchTimer {
     timer: Instant,
     hard_limit: u128,
     soft_limit: u128,
+    nodes_time: Nodes,
 }
 
 impl SearchTimer {
-    pub fn new(time_to_use: Milliseconds) -> Self {
+    #[allow(clippy::cast_sign_loss)]
+    pub fn new(time_to_use: Milliseconds, nodes_time: Nodes) -> Self {
+        let hard_limit = time_to_use.saturating_mul(1000);
         Self {
             timer: Instant::now(),
-            hard_limit: time_to_use.saturating_mul(1000),
-            soft_limit: time_to_use.saturating_mul(1000) / 2,
+            hard_limit,
+            soft_limit: hard_limit * tm_soft_percent() as u128 / 100,
+            nodes_time,
         }
     }
 
-    pub fn is_expired(&self) -> bool {
-        (self.timer.elapsed().as_micros()) > self.hard_limit
+    // in microseconds, counting nodes_time nodes as one millisecond when it is set
+    fn elapsed(&self, nodes: Nodes) -> u128 {
+        if self.nodes_time > 0 {
+            u128::from(nodes) * 1000 / u128::from(self.nodes_time)
+        } else {
+            self.timer.elapsed().as_micros()
+        }
     }
 
-    pub fn is_soft_expired(&self) -> bool {
-        self.timer.elapsed().as_micros() > self.soft_limit
+    pub fn is_expired(&self, nodes: Nodes) -> bool {
+        self.elapsed(nodes) > self.hard_limit
     }
 
+    pub fn is_soft_expired(&self, nodes: Nodes) -> bool {
+        self.elapsed(nodes) > self.soft_limit
+    }
+
+    // The soft limit grows when the aspiration windows keep failing, the best move is
+    // unstable or the score is dropping, and shrinks when the best move soaks up most nodes.
     #[allow(clippy::cast_sign_loss)]
     #[allow(clippy::cast_precision_loss)]
-    pub fn update_soft_limit(&mut self, widenings: &[u16], node_frac: f64) {
+    pub fn update_soft_limit(
+        &mut self,
+        widenings: &[u16],
+        node_frac: f64,
+        stability: u16,
+        score_drop: EvalScore,
+    ) {
         let avg_w = f64::from(widenings.iter().sum::<u16>()) / widenings.len() as f64;
 
-        let asp_scale: f64 = 0.04 * avg_w * avg_w;
-        let node_scale: f64 = (1.25 - node_frac) * 1.35;
+        let asp_scale = f64::from(tm_asp_scale()) / 100.0 * avg_w * avg_w;
+        let node_scale =
+            (f64::from(tm_node_base()) / 100.0 - node_frac) * f64::from(tm_node_scale()) / 100.0;
+
+        let stable_iterations = i32::from(stability).min(tm_stability_max());
+        let stability_scale =
+            f64::from(tm_stability_base() - tm_stability_step() * stable_iterations) / 100.0;
+
+        let drop_extension =
+            (score_drop.max(0) * tm_score_drop_scale() / 10).min(tm_score_drop_max());
+        let score_scale = 1.0 + f64::from(drop_extension) / 100.0;
 
-        let scale = asp_scale + node_scale;
+        let scale = (asp_scale + node_scale) * stability_scale * score_scale;
         self.soft_limit = ((self.hard_limit as f64) * scale) as u128;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Milliseconds, SearchTimer, TimeArgs, TimeManager};
+    use crate::board::board_representation::Color;
+
+    #[test]
+    fn never_flags() {
+        const OVERHEAD: Milliseconds = 25;
+        const LATENCY: Milliseconds = 5;
+        const MOVES: usize = 120;
+
+        let manager = TimeManager::new(OVERHEAD);
+        let controls = [
+            (60_000, 0, 0),
+            (10_000, 100, 0),
+            (1_000, 10, 0),
+            (180_000, 2_000, 0),
+            (60_000, 0, 40),
+            (10_000, 0, 1),
+            (2_000, 0, 10),
+        ];
+
+        for (time, inc, moves_to_go) in controls {
+            let args = TimeArgs {
+                time: [time; 2],
+                inc: [inc; 2],
+                move_time: 0,
+                moves_to_go,
+            };
+
+            let lowest_clock = manager.simulate_clock(args, Color::White, MOVES, LATENCY);
+            assert!(
+                lowest_clock.is_some(),
+                "flagged at {time}+{inc}, {moves_to_go} to go"
+            );
+        }
+    }
+
+    #[test]
+    fn node_clock_ignores_the_gui_clock() {
+        let mut manager = TimeManager::new(0);
+        manager.set_nodes_time(100);
+
+        let args = TimeArgs {
+            time: [10_000, 10_000],
+            inc: [100, 100],
+            move_time: 0,
+            moves_to_go: 0,
+        };
+        assert_eq!(manager.node_clock(args, Color::White), args);
+
+        // 200ms worth of nodes spent, 100ms of increment gained, whatever the GUI says
+        manager.spend_nodes(args, Color::White, 20_000);
+        let later = TimeArgs {
+            time: [1, 1],
+            ..args
+        };
+        assert_eq!(manager.node_clock(later, Color::White).time[0], 9_900);
+
+        // searches without a clock leave it alone
+        manager.spend_nodes(TimeArgs::default(), Color::White, 50_000);
+        let movetime = TimeArgs {
+            move_time: 100,
+            ..args
+        };
+        manager.spend_nodes(movetime, Color::White, 50_000);
+        assert_eq!(manager.node_clock(later, Color::White).time[0], 9_900);
+
+        // the other side keeps a clock of its own
+        assert_eq!(manager.node_clock(later, Color::Black).time[1], 1);
+
+        let timer = SearchTimer::new(50, 100);
+        assert!(!timer.is_expired(5_000));
+        assert!(timer.is_expired(5_001));
+    }
+
+    #[test]
+    fn node_clock_adds_each_period() {
+        let mut manager = TimeManager::new(0);
+        manager.set_nodes_time(100);
+
+        // 40 moves in 1000ms, each using 20ms worth of nodes
+        let mut clock = 1_000;
+        for moves_to_go in (1..=40).rev().cycle().take(200) {
+            let args = TimeArgs {
+                time: [clock, 1_000],
+                moves_to_go,
+                ..TimeArgs::default()
+            };
+            let args = manager.node_clock(args, Color::White);
+            assert!(args.time[0] > 20, "ran out of nodes");
+            manager.spend_nodes(args, Color::White, 2_000);
+            clock = 1;
+        }
+
+        // the first period and the five that followed, less 200 moves of 20ms
+        let args = TimeArgs {
+            time: [1, 1],
+            moves_to_go: 40,
+            ..TimeArgs::default()
+        };
+        assert_eq!(manager.node_clock(args, Color::White).time[0], 2_000);
+    }
+}
@@ -1,4 +1,8 @@
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    sync::atomic::Ordering,
+};
 
 use crate::{
     board::chess_move::Move,
@@ -7,6 +11,8 @@ use crate::{
     search::search::{Depth, Ply},
 };
 
+use super::tt_memory::TableMemory;
+
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct TTFlag(u8);
 
@@ -160,22 +166,28 @@ impl From<TTEntry> for u64 {
 
 #[derive(Debug)]
 pub struct TranspositionTable {
-    table: Vec<AtomicU64>,
+    table: TableMemory,
     age: u8,
 }
 
 impl TranspositionTable {
-    pub fn new(megabytes: usize) -> Self {
+    const FILE_MAGIC: [u8; 8] = *b"WAHOOTT\0";
+    const FILE_VERSION: u32 = 1;
+
+    pub fn new(megabytes: usize, threads: usize) -> Self {
         const BYTES_PER_MB: usize = 1024 * 1024;
 
         let bytes = megabytes * BYTES_PER_MB;
         let entries = bytes / TTEntry::BYTES;
-        let mut table = vec![];
-        table.resize_with(entries, AtomicU64::default);
+        let table = TableMemory::new(entries, threads);
 
         Self { table, age: 0 }
     }
 
+    pub const fn large_pages(&self) -> bool {
+        self.table.huge_pages()
+    }
+
     fn table_index(&self, hash: ZobristHash) -> usize {
         // use lower bits for index
         hash.as_usize() % self.table.len()
@@ -235,7 +247,7 @@ impl TranspositionTable {
         assert!(self.age <= AGE_MAX, "TT AGE EXCEEDED AGE_MAX");
         if self.age == AGE_MAX {
             self.age = 0;
-            self.table.iter_mut().for_each(|x| {
+            self.table.iter().for_each(|x| {
                 let mut entry = TTEntry::from(x.load(Ordering::Relaxed));
                 let flag = entry.age_and_flag.flag();
                 entry.age_and_flag = AgeAndFlag::new(0, flag);
@@ -246,12 +258,79 @@ impl TranspositionTable {
         self.age += 1;
     }
 
-    pub fn reset(&mut self) {
-        self.table
-            .iter_mut()
-            .for_each(|x| *x = AtomicU64::default());
+    pub fn reset(&mut self, threads: usize) {
+        self.table.clear(threads);
         self.age = 0;
     }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        // header: magic, format version, age, entry count
+        file.write_all(&Self::FILE_MAGIC)?;
+        file.write_all(&Self::FILE_VERSION.to_le_bytes())?;
+        file.write_all(&[self.age])?;
+        file.write_all(&(self.table.len() as u64).to_le_bytes())?;
+
+        for x in self.table.iter() {
+            file.write_all(&x.load(Ordering::Relaxed).to_le_bytes())?;
+        }
+
+        file.flush()
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is not a hash file of the current
+    /// format version, or was saved with a different table size. The table is left as it
+    /// was on any error.
+    pub fn load(&mut self, path: &str) -> io::Result<()> {
+        fn invalid(msg: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        }
+
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        if magic != Self::FILE_MAGIC {
+            return Err(invalid("not a Wahoo hash file"));
+        }
+
+        let mut version = [0; 4];
+        file.read_exact(&mut version)?;
+        if u32::from_le_bytes(version) != Self::FILE_VERSION {
+            return Err(invalid("unsupported hash file version"));
+        }
+
+        let mut age = [0; 1];
+        file.read_exact(&mut age)?;
+
+        let mut entries = [0; 8];
+        file.read_exact(&mut entries)?;
+        if u64::from_le_bytes(entries) != self.table.len() as u64 {
+            return Err(invalid("hash file size does not match current Hash size"));
+        }
+
+        // read it all before storing anything, so a truncated file can't leave a half
+        // loaded table behind
+        let mut data = [0; 8];
+        let mut loaded = Vec::with_capacity(self.table.len());
+        for _ in 0..self.table.len() {
+            file.read_exact(&mut data)?;
+            loaded.push(u64::from_le_bytes(data));
+        }
+
+        for (x, data) in self.table.iter().zip(loaded) {
+            x.store(data, Ordering::Relaxed);
+        }
+
+        self.age = age[0];
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -266,7 +345,7 @@ mod tests {
 
     #[test]
     fn probe_works() {
-        let mut tt = TranspositionTable::new(16);
+        let mut tt = TranspositionTable::new(16, 1);
         tt.age_table();
         let board = Board::from_fen(START_FEN);
         let best_score = 16;
@@ -292,6 +371,40 @@ mod tests {
         assert_eq!(tt.probe(other_hash), None);
     }
 
+    #[test]
+    fn save_load_round_trip() {
+        let mut tt = TranspositionTable::new(1, 1);
+        tt.age_table();
+        let board = Board::from_fen(START_FEN);
+        let hash = ZobristHash::complete(&board);
+        let mv = Move::from_string("e2e4", &board);
+        tt.store(TTFlag::LOWER_BOUND, 37, hash, 2, 9, mv);
+
+        let path = std::env::temp_dir().join("wahoo_tt_round_trip.hash");
+        let path = path.to_str().unwrap();
+        tt.save(path).unwrap();
+
+        let mut loaded = TranspositionTable::new(1, 1);
+        loaded.load(path).unwrap();
+        assert_eq!(loaded.probe(hash), tt.probe(hash));
+        assert_eq!(loaded.age, tt.age);
+
+        let mut wrong_size = TranspositionTable::new(2, 1);
+        assert!(wrong_size.load(path).is_err());
+        assert_eq!(wrong_size.probe(hash), None);
+
+        // a truncated file leaves the current table as it was
+        let bytes = std::fs::read(path).unwrap();
+        std::fs::write(path, &bytes[..bytes.len() - 8]).unwrap();
+        let mut current = TranspositionTable::new(1, 1);
+        current.store(TTFlag::EXACT, 12, hash, 0, 3, mv);
+        let before = current.probe(hash);
+        assert!(current.load(path).is_err());
+        assert_eq!(current.probe(hash), before);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn flag_packing() {
         let age = 43;
@@ -0,0 +1,118 @@
+use std::{
+    alloc::{self, Layout},
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+};
+
+const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+const CACHE_LINE_SIZE: usize = 64;
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn madvise(addr: *mut std::ffi::c_void, len: usize, advice: i32) -> i32;
+}
+
+#[cfg(target_os = "linux")]
+fn advise_huge_pages(ptr: *mut u8, len: usize) -> bool {
+    const MADV_HUGEPAGE: i32 = 14;
+
+    // SAFETY: the range is a single allocation of len bytes, and MADV_HUGEPAGE is only a hint.
+    let result = unsafe { madvise(ptr.cast(), len, MADV_HUGEPAGE) };
+
+    // madvise succeeds even when THP is switched off system wide
+    let thp_disabled = std::fs::read_to_string("/sys/kernel/mm/transparent_hugepage/enabled")
+        .map_or(true, |s| s.contains("[never]"));
+
+    result == 0 && !thp_disabled
+}
+
+#[cfg(not(target_os = "linux"))]
+const fn advise_huge_pages(_ptr: *mut u8, _len: usize) -> bool {
+    false
+}
+
+#[derive(Debug)]
+pub struct TableMemory {
+    ptr: NonNull<AtomicU64>,
+    len: usize,
+    layout: Layout,
+    huge_pages: bool,
+}
+
+// SAFETY: the memory is uniquely owned, and every element is an atomic.
+unsafe impl Send for TableMemory {}
+unsafe impl Sync for TableMemory {}
+
+impl TableMemory {
+    #[allow(clippy::cast_ptr_alignment)] // layout alignment is at least 64
+    pub fn new(len: usize, threads: usize) -> Self {
+        let bytes = len * std::mem::size_of::<AtomicU64>();
+        let align = if bytes >= HUGE_PAGE_SIZE {
+            HUGE_PAGE_SIZE
+        } else {
+            CACHE_LINE_SIZE
+        };
+        let layout = Layout::from_size_align(bytes, align).unwrap();
+
+        if bytes == 0 {
+            return Self {
+                ptr: NonNull::dangling(),
+                len: 0,
+                layout,
+                huge_pages: false,
+            };
+        }
+
+        // SAFETY: layout has a non-zero size, and all zeroes is a valid AtomicU64.
+        let raw = unsafe { alloc::alloc_zeroed(layout) };
+        let Some(ptr) = NonNull::new(raw.cast::<AtomicU64>()) else {
+            alloc::handle_alloc_error(layout);
+        };
+
+        let huge_pages = align == HUGE_PAGE_SIZE && advise_huge_pages(raw, bytes);
+
+        let memory = Self {
+            ptr,
+            len,
+            layout,
+            huge_pages,
+        };
+
+        // touch every page up front so the page faults don't land in the first search
+        memory.clear(threads);
+        memory
+    }
+
+    pub const fn huge_pages(&self) -> bool {
+        self.huge_pages
+    }
+
+    pub fn clear(&self, threads: usize) {
+        let chunk_size = (self.len / threads.max(1)).max(1);
+        thread::scope(|s| {
+            for chunk in self.chunks(chunk_size) {
+                s.spawn(|| chunk.iter().for_each(|x| x.store(0, Ordering::Relaxed)));
+            }
+        });
+    }
+}
+
+impl Deref for TableMemory {
+    type Target = [AtomicU64];
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: ptr points to len initialized atomics (or is dangling with len 0).
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for TableMemory {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: ptr was allocated with this exact layout in new().
+            unsafe { alloc::dealloc(self.ptr.as_ptr().cast::<u8>(), self.layout) };
+        }
+    }
+}
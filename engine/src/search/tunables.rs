@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::{
+    eval::evaluation::EvalScore,
+    search::{search::Depth, time_management::Milliseconds},
+};
+
+use super::late_move_reductions;
+
+pub const TUNING_ENABLED: bool = cfg!(feature = "tune");
+
+#[derive(Debug)]
+pub struct Tunable {
+    pub name: &'static str,
+    pub default: i32,
+    pub min: i32,
+    pub max: i32,
+    pub step: i32,
+    value: &'static AtomicI32,
+}
+
+impl Tunable {
+    pub fn value(&self) -> i32 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    // OpenBench SPSA input: name, type, value, min, max, c_end, r_end
+    pub fn openbench_string(&self) -> String {
+        const R_END: f64 = 0.002;
+        format!(
+            "{}, int, {}, {}, {}, {}, {R_END}",
+            self.name,
+            self.value(),
+            self.min,
+            self.max,
+            self.step
+        )
+    }
+}
+
+// Each entry becomes an accessor function. Without the "tune" feature it is a const fn
+// returning the default, so the search compiles exactly as if the value were a literal.
+macro_rules! tunable {
+    ($($name:ident: $t:ty = $default:literal, $min:literal, $max:literal, $step:literal;)*) => {
+        $(
+            mod $name {
+                pub static VALUE: super::AtomicI32 = super::AtomicI32::new($default);
+            }
+
+            #[cfg(not(feature = "tune"))]
+            pub const fn $name() -> $t {
+                $default
+            }
+
+            // values are clamped to their range, so the cast is lossless
+            #[cfg(feature = "tune")]
+            #[allow(clippy::cast_sign_loss)]
+            pub fn $name() -> $t {
+                $name::VALUE.load(Ordering::Relaxed) as $t
+            }
+        )*
+
+        pub static TUNABLES: &[Tunable] = &[
+            $(
+                Tunable {
+                    name: stringify!($name),
+                    default: $default,
+                    min: $min,
+                    max: $max,
+                    step: $step,
+                    value: &$name::VALUE,
+                },
+            )*
+        ];
+    };
+}
+
+tunable! {
+    asp_window_min_depth: Depth = 7, 1, 16, 1;
+    asp_window_init_window: EvalScore = 12, 4, 50, 2;
+    asp_window_init_delta: EvalScore = 16, 4, 64, 2;
+    asp_window_full_search_bounds: EvalScore = 3500, 1000, 5000, 100;
+    iir_min_depth: Depth = 3, 1, 8, 1;
+    rfp_min_depth: Depth = 8, 2, 16, 1;
+    rfp_margin: EvalScore = 90, 30, 200, 8;
+    nmp_min_depth: Depth = 3, 1, 8, 1;
+    razor_max_depth: Depth = 3, 1, 6, 1;
+    razor_margin: EvalScore = 250, 100, 500, 20;
+    probcut_min_depth: Depth = 5, 3, 10, 1;
+    probcut_margin: EvalScore = 200, 80, 400, 10;
+    probcut_reduction: Depth = 4, 2, 6, 1;
+    fp_max_depth: Depth = 6, 1, 10, 1;
+    fp_base: EvalScore = 80, 20, 200, 10;
+    fp_margin: EvalScore = 90, 30, 200, 8;
+    pruning_threshold: EvalScore = 700, 300, 1500, 40;
+    see_min_depth: Depth = 6, 1, 12, 1;
+    see_margin: EvalScore = 90, 20, 200, 8;
+    delta_margin: EvalScore = 200, 50, 400, 20;
+    lmr_min_depth: Depth = 3, 1, 8, 1;
+    // the LMR formula constants are in hundredths, and must match lmr_init.rs
+    lmr_base: i32 = 77, 20, 150, 5;
+    lmr_divisor: i32 = 236, 150, 400, 10;
+    // time management, in percent unless noted otherwise
+    tm_base_divisor: Milliseconds = 15, 8, 40, 1;
+    tm_inc_percent: Milliseconds = 80, 40, 100, 5;
+    tm_mtg_reserve: Milliseconds = 2, 0, 8, 1;
+    tm_max_percent: Milliseconds = 50, 20, 80, 5;
+    tm_emergency_time: Milliseconds = 2000, 200, 5000, 100;
+    tm_emergency_divisor: Milliseconds = 40, 10, 80, 2;
+    tm_emergency_inc_percent: Milliseconds = 50, 20, 100, 5;
+    tm_soft_percent: i32 = 50, 20, 80, 5;
+    tm_asp_scale: i32 = 4, 0, 20, 1;
+    tm_node_base: i32 = 125, 80, 200, 5;
+    tm_node_scale: i32 = 135, 50, 250, 10;
+    tm_stability_base: i32 = 120, 80, 200, 5;
+    tm_stability_step: i32 = 8, 0, 20, 1;
+    tm_stability_max: i32 = 5, 1, 10, 1;
+    // extension per 10cp the score dropped since the last iteration
+    tm_score_drop_scale: i32 = 5, 0, 20, 1;
+    tm_score_drop_max: i32 = 60, 0, 200, 10;
+}
+
+// Returns false if there is no tunable with this name. Values are only used by the
+// search when the "tune" feature is enabled.
+pub fn set_tunable(name: &str, value: i32) -> bool {
+    let Some(tunable) = TUNABLES.iter().find(|t| t.name == name) else {
+        return false;
+    };
+
+    tunable
+        .value
+        .store(value.clamp(tunable.min, tunable.max), Ordering::Relaxed);
+
+    if name.starts_with("lmr_") {
+        late_move_reductions::rebuild_table();
+    }
+
+    true
+}
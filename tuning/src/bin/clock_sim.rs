@@ -0,0 +1,166 @@
+use engine::{
+    board::board_representation::Color,
+    search::time_management::{Milliseconds, TimeArgs, TimeManager},
+};
+use std::fs::read_to_string;
+
+// Replays logged games against the time manager. Every clocked "go" is fed back into it,
+// and the time it would allocate plus the GUI's latency is checked against the clock that
+// was logged for that move. The clock of our next move also shows how long the move really
+// took, which catches searches that ran past their allocation. The log is the engine's side
+// of the UCI conversation, e.g. a cutechess-cli debug log: "ucinewgame" starts a game, and
+// each "position" + "go" pair is one of our moves.
+//
+// usage: clock_sim <log file> [move overhead] [latency per move]
+
+struct LoggedMove {
+    args: TimeArgs,
+    color: Color,
+}
+
+fn side_to_move(position: &[&str]) -> Color {
+    let moves_index = position.iter().position(|&token| token == "moves");
+    let played = moves_index.map_or(0, |i| position.len() - i - 1);
+
+    let start_color = match position.get(1) {
+        Some(&"fen") if position.get(3) == Some(&"b") => Color::Black,
+        _ => Color::White,
+    };
+
+    if played % 2 == 0 {
+        start_color
+    } else {
+        start_color.flip()
+    }
+}
+
+fn parse_go(tokens: &[&str]) -> TimeArgs {
+    let mut args = TimeArgs::default();
+    let mut iter = tokens.iter();
+    while let Some(&token) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .and_then(|x| x.parse::<Milliseconds>().ok())
+                .unwrap_or(0)
+        };
+
+        match token {
+            "wtime" => args.time[Color::White.as_index()] = value(),
+            "btime" => args.time[Color::Black.as_index()] = value(),
+            "winc" => args.inc[Color::White.as_index()] = value(),
+            "binc" => args.inc[Color::Black.as_index()] = value(),
+            "movetime" => args.move_time = value(),
+            "movestogo" => args.moves_to_go = value() as u64,
+            _ => (),
+        }
+    }
+
+    args
+}
+
+fn load_games(file_name: &str) -> Vec<Vec<LoggedMove>> {
+    let mut games: Vec<Vec<LoggedMove>> = vec![];
+    let mut new_game = true;
+    let mut color = Color::White;
+
+    for line in read_to_string(file_name).unwrap().lines() {
+        // log lines may carry a prefix, so look for the command anywhere in the line
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let Some(command_index) = tokens
+            .iter()
+            .position(|&token| matches!(token, "ucinewgame" | "position" | "go"))
+        else {
+            continue;
+        };
+
+        let command = &tokens[command_index..];
+        match command[0] {
+            "ucinewgame" => new_game = true,
+            "position" => color = side_to_move(command),
+            _ => {
+                let args = parse_go(&command[1..]);
+                if args.time[color.as_index()] == 0 {
+                    continue; // only clocked searches are of interest
+                }
+
+                if new_game {
+                    games.push(vec![]);
+                    new_game = false;
+                }
+                games.last_mut().unwrap().push(LoggedMove { args, color });
+            }
+        }
+    }
+
+    games
+}
+
+// how long a move really took, from the clock logged for the next one
+fn time_used(current: &LoggedMove, next: &LoggedMove) -> Option<Milliseconds> {
+    let c = current.color.as_index();
+    let (args, next_args) = (current.args, next.args);
+
+    // a new period adds time to the clock, which hides the time used
+    if current.color != next.color || next_args.moves_to_go > args.moves_to_go {
+        return None;
+    }
+
+    (args.time[c] + args.inc[c]).checked_sub(next_args.time[c])
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let file_name: &str = args.get(1).expect("expected log file to read!");
+    let overhead: Milliseconds = args
+        .get(2)
+        .map_or_else(|| 25, |x| x.parse::<Milliseconds>().unwrap_or(25));
+    let latency: Milliseconds = args
+        .get(3)
+        .map_or_else(|| 5, |x| x.parse::<Milliseconds>().unwrap_or(5));
+
+    let manager = TimeManager::new(overhead);
+    let games = load_games(file_name);
+
+    let mut moves = 0;
+    let mut flagged = 0;
+    let mut overruns = 0;
+    let mut lowest_margin = Milliseconds::MAX;
+    for (i, game) in games.iter().enumerate() {
+        for (j, logged) in game.iter().enumerate() {
+            let c = logged.color.as_index();
+            let clock = logged.args.time[c];
+            let allocated = manager.calculate_search_time(logged.args, logged.color) + latency;
+            moves += 1;
+
+            if allocated >= clock {
+                flagged += 1;
+                println!(
+                    "game {} move {}: {allocated}ms allocated with {clock}ms on the clock",
+                    i + 1,
+                    j + 1
+                );
+            } else {
+                lowest_margin = lowest_margin.min(clock - allocated);
+            }
+
+            if let Some(used) = game.get(j + 1).and_then(|next| time_used(logged, next)) {
+                if used > allocated {
+                    overruns += 1;
+                    println!(
+                        "game {} move {}: took {used}ms of {allocated}ms allocated",
+                        i + 1,
+                        j + 1
+                    );
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} games, {moves} moves replayed: {flagged} would flag, {overruns} overran",
+        games.len()
+    );
+    if lowest_margin != Milliseconds::MAX {
+        println!("lowest margin {lowest_margin}ms");
+    }
+}
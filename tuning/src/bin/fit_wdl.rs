@@ -0,0 +1,285 @@
+#![allow(clippy::needless_range_loop)]
+
+use engine::{
+    board::board_representation::{Board, Color},
+    board::zobrist_stack::ZobristStack,
+    create_thread_data,
+    eval::evaluation::{evaluate, EvalScore},
+    eval::wdl::{material, MATERIAL_MAX, MATERIAL_MIN, MATERIAL_NORM},
+    search::history_table::History,
+    search::search::{write_stop_flag, Depth, SearchLimit, SearchOptions, Searcher},
+    search::transposition_table::TranspositionTable,
+    tablebase::probe::Syzygy,
+};
+use std::{fs::read_to_string, thread};
+
+// Fits the engine's win-rate model to datagen output by maximum likelihood:
+//     win  = 1 / (1 + exp((a - score) / b))
+//     loss = 1 / (1 + exp((a + score) / b))
+// with a and b cubic polynomials of the material on the board.
+//
+// Positions are scored by a search of the given depth. At depth 0, the search score datagen
+// recorded with the position is used, or the static eval for older data without scores.
+//
+// usage: fit_wdl <data file> [threads] [search depth]
+
+type Coeffs = [f64; 4];
+
+struct Position {
+    score: f64,
+    material: f64,
+    result: f64,
+}
+
+#[derive(Clone, Copy)]
+struct Params {
+    a: Coeffs,
+    b: Coeffs,
+}
+
+impl Params {
+    fn add(self, rhs: Self) -> Self {
+        let mut result = self;
+        for i in 0..4 {
+            result.a[i] += rhs.a[i];
+            result.b[i] += rhs.b[i];
+        }
+        result
+    }
+}
+
+const ZERO: Params = Params {
+    a: [0.0; 4],
+    b: [0.0; 4],
+};
+
+// coefficients are stored highest power first, matching the engine
+fn powers(m: f64) -> Coeffs {
+    [m * m * m, m * m, m, 1.0]
+}
+
+fn polynomial(coeffs: Coeffs, m: f64) -> f64 {
+    coeffs.iter().fold(0.0, |acc, &c| acc.mul_add(m, c))
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn probabilities(params: &Params, pos: &Position) -> (f64, f64, f64) {
+    let a = polynomial(params.a, pos.material);
+    let b = polynomial(params.b, pos.material);
+    let win = sigmoid((pos.score - a) / b);
+    let loss = sigmoid((-pos.score - a) / b);
+    (win, 1.0 - win - loss, loss)
+}
+
+const MIN_PROBABILITY: f64 = 1e-9;
+
+fn outcome_probability(params: &Params, pos: &Position) -> f64 {
+    let (win, draw, loss) = probabilities(params, pos);
+    let p = if pos.result > 0.75 {
+        win
+    } else if pos.result < 0.25 {
+        loss
+    } else {
+        draw
+    };
+    p.max(MIN_PROBABILITY)
+}
+
+// gradient of the negative log likelihood of a single position
+fn gradient(params: &Params, pos: &Position) -> Params {
+    let a = polynomial(params.a, pos.material);
+    let b = polynomial(params.b, pos.material);
+    let u = (pos.score - a) / b;
+    let v = (-pos.score - a) / b;
+    let (win, loss) = (sigmoid(u), sigmoid(v));
+    let (dwin, dloss) = (win * (1.0 - win), loss * (1.0 - loss));
+
+    // derivatives of the outcome probability with respect to a and b
+    let (dp_da, dp_db) = if pos.result > 0.75 {
+        (-dwin / b, -dwin * u / b)
+    } else if pos.result < 0.25 {
+        (-dloss / b, -dloss * v / b)
+    } else {
+        ((dwin + dloss) / b, (dwin * u + dloss * v) / b)
+    };
+
+    let p = outcome_probability(params, pos);
+    let x = powers(pos.material);
+    let mut result = ZERO;
+    for i in 0..4 {
+        result.a[i] = -dp_da / p * x[i];
+        result.b[i] = -dp_db / p * x[i];
+    }
+    result
+}
+
+fn total_gradient(params: &Params, positions: &[Position], threads: usize) -> Params {
+    let size = (positions.len() / threads).max(1);
+    thread::scope(|s| {
+        positions
+            .chunks(size)
+            .map(|chunk| {
+                s.spawn(|| {
+                    chunk
+                        .iter()
+                        .fold(ZERO, |acc, pos| acc.add(gradient(params, pos)))
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|p| p.join().unwrap())
+            .fold(ZERO, Params::add)
+    })
+}
+
+fn log_likelihood(params: &Params, positions: &[Position], threads: usize) -> f64 {
+    let size = (positions.len() / threads).max(1);
+    thread::scope(|s| {
+        positions
+            .chunks(size)
+            .map(|chunk| {
+                s.spawn(|| {
+                    chunk
+                        .iter()
+                        .map(|pos| outcome_probability(params, pos).ln())
+                        .sum::<f64>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|p| p.join().unwrap())
+            .sum::<f64>()
+    }) / (positions.len() as f64)
+}
+
+fn search_score(board: &Board, depth: Depth, tt: &TranspositionTable) -> EvalScore {
+    create_thread_data!(thread_data);
+    write_stop_flag(false);
+
+    let mut searcher = Searcher::new(
+        vec![SearchLimit::Depth(depth)],
+        &ZobristStack::new(board),
+        &History::new(),
+        tt,
+        Syzygy::new(),
+        thread_data,
+        SearchOptions::default(),
+    );
+    searcher.go::<true>(board, false).score
+}
+
+// known wins like KBNK score far above this, and datagen's searches don't always convert them
+const MAX_SCORE: EvalScore = 3000;
+
+fn load_positions(file_name: &str, depth: Depth) -> Vec<Position> {
+    let mut tt = TranspositionTable::new(16, 1);
+    let mut positions = vec![];
+    for line in read_to_string(file_name).unwrap().lines() {
+        let (fen, r) = line.split_once('[').unwrap();
+        let (result, recorded) = r.split_once(']').unwrap();
+        let result = result.parse::<f64>().unwrap();
+        let board = Board::from_fen(fen);
+
+        // results and recorded scores are from white's point of view
+        let score = match recorded.trim().parse::<EvalScore>() {
+            Ok(score) if depth == 0 => score,
+            _ => {
+                let stm_score = if depth > 0 {
+                    tt.reset(1);
+                    search_score(&board, depth, &tt)
+                } else {
+                    evaluate(&board)
+                };
+                match board.color_to_move {
+                    Color::White => stm_score,
+                    Color::Black => -stm_score,
+                }
+            }
+        };
+        if score.abs() > MAX_SCORE {
+            continue;
+        }
+        let material = material(&board).clamp(MATERIAL_MIN, MATERIAL_MAX);
+
+        positions.push(Position {
+            score: f64::from(score),
+            material: f64::from(material) / MATERIAL_NORM,
+            result,
+        });
+    }
+
+    positions
+}
+
+fn print_coeffs(name: &str, coeffs: Coeffs) {
+    let values = coeffs.map(|c| format!("{c:.3}")).join(", ");
+    println!("const {name}: [f64; 4] = [{values}];");
+}
+
+fn adam_step(param: &mut f64, grad: f64, momentum: &mut f64, velocity: &mut f64) {
+    const LEARNING_RATE: f64 = 0.5;
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+
+    *momentum = BETA1 * *momentum + (1.0 - BETA1) * grad;
+    *velocity = BETA2 * *velocity + (1.0 - BETA2) * grad * grad;
+    *param -= LEARNING_RATE * *momentum / (EPSILON + velocity.sqrt());
+}
+
+fn main() {
+    const MAX_ITERATIONS: u32 = 20000;
+    const CHECK_FREQ: u32 = 500;
+
+    let args: Vec<String> = std::env::args().collect();
+    let file_name: &str = args.get(1).expect("expected data file to read!");
+    let threads: usize = args
+        .get(2)
+        .map_or_else(|| 1, |x| x.parse::<usize>().unwrap_or(1));
+    let depth: Depth = args
+        .get(3)
+        .map_or_else(|| 0, |x| x.parse::<Depth>().unwrap_or(0));
+
+    let positions = load_positions(file_name, depth);
+    println!("{} positions loaded", positions.len());
+
+    // start from a flat model: 50% wins at +200, with a spread of 50
+    let mut params = Params {
+        a: [0.0, 0.0, 0.0, 200.0],
+        b: [0.0, 0.0, 0.0, 50.0],
+    };
+    let mut momentum = ZERO;
+    let mut velocity = ZERO;
+
+    let count = positions.len() as f64;
+    for iteration in 1..=MAX_ITERATIONS {
+        let grad = total_gradient(&params, &positions, threads);
+        for i in 0..4 {
+            adam_step(
+                &mut params.a[i],
+                grad.a[i] / count,
+                &mut momentum.a[i],
+                &mut velocity.a[i],
+            );
+            adam_step(
+                &mut params.b[i],
+                grad.b[i] / count,
+                &mut momentum.b[i],
+                &mut velocity.b[i],
+            );
+        }
+
+        if iteration % CHECK_FREQ == 0 {
+            println!(
+                "Iteration: {iteration}\nLog likelihood: {}\n",
+                log_likelihood(&params, &positions, threads)
+            );
+        }
+    }
+
+    print_coeffs("A_COEFFS", params.a);
+    print_coeffs("B_COEFFS", params.b);
+}
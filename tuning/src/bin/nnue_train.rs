@@ -0,0 +1,495 @@
+#![allow(clippy::needless_range_loop)]
+
+use engine::{
+    bitloop,
+    board::board_representation::{Board, Color, Piece, Square},
+    eval::evaluation::{evaluate, EvalScore},
+    eval::nnue::{
+        feature_index, HIDDEN_SIZE, INPUT_SIZE, MAX_FEATURE_WEIGHT, MAX_OUTPUT_WEIGHT, QA, QB,
+        SCALE,
+    },
+};
+use std::{
+    fs::{read_to_string, File},
+    io::{BufReader, BufWriter, Read, Write},
+    str::FromStr,
+    thread,
+    time::Instant,
+};
+
+// Trains a net for the engine's "nnue" feature from datagen output, on the CPU with minibatch
+// Adam. Each position's target blends the game result with the search score datagen recorded,
+// or with the static eval for older data without scores. After every epoch the quantized net
+// is written to the output file, along with a float checkpoint that training can resume from.
+//
+// usage: nnue-train <data file> <output net> [--threads N] [--epochs N] [--batch N] [--lr X]
+//        [--lr-drop N] [--lr-gamma X] [--wdl X] [--validation X] [--resume <checkpoint>]
+
+// parameters are kept flat, in the order of the net file
+const FEATURE_WEIGHTS: usize = 0;
+const FEATURE_BIAS: usize = FEATURE_WEIGHTS + INPUT_SIZE * HIDDEN_SIZE;
+const OUTPUT_WEIGHTS: usize = FEATURE_BIAS + HIDDEN_SIZE;
+const OUTPUT_BIAS: usize = OUTPUT_WEIGHTS + 2 * HIDDEN_SIZE;
+const PARAM_COUNT: usize = OUTPUT_BIAS + 1;
+
+const CHECKPOINT_MAGIC: [u8; 8] = *b"WAHOOCKP";
+const CHECKPOINT_VERSION: u32 = 1;
+
+struct Options {
+    threads: usize,
+    epochs: u32,
+    batch_size: usize,
+    lr: f32,
+    // the learning rate is multiplied by lr_gamma every lr_drop epochs
+    lr_drop: u32,
+    lr_gamma: f32,
+    // weight of the game result in the target, the rest is the score
+    wdl: f32,
+    // fraction of the positions held out to report validation loss
+    validation: f32,
+    resume: Option<String>,
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid value for {flag}: {value}"))
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Self {
+        let mut options = Self {
+            threads: 1,
+            epochs: 30,
+            batch_size: 16384,
+            lr: 0.001,
+            lr_drop: 10,
+            lr_gamma: 0.1,
+            wdl: 0.25,
+            validation: 0.05,
+            resume: None,
+        };
+
+        let mut iter = args.iter();
+        while let Some(flag) = iter.next() {
+            let value = iter
+                .next()
+                .unwrap_or_else(|| panic!("expected a value for {flag}"));
+            match flag.as_str() {
+                "--threads" => options.threads = parse(flag, value),
+                "--epochs" => options.epochs = parse(flag, value),
+                "--batch" => options.batch_size = parse(flag, value),
+                "--lr" => options.lr = parse(flag, value),
+                "--lr-drop" => options.lr_drop = parse(flag, value),
+                "--lr-gamma" => options.lr_gamma = parse(flag, value),
+                "--wdl" => options.wdl = parse(flag, value),
+                "--validation" => options.validation = parse(flag, value),
+                "--resume" => options.resume = Some(value.clone()),
+                _ => panic!("unknown option {flag}"),
+            }
+        }
+
+        options.threads = options.threads.max(1);
+        options.batch_size = options.batch_size.max(1);
+        options.lr_drop = options.lr_drop.max(1);
+        options
+    }
+
+    fn lr(&self, epoch: u32) -> f32 {
+        self.lr * self.lr_gamma.powi(((epoch - 1) / self.lr_drop) as i32)
+    }
+}
+
+struct Position {
+    // active features from white's and black's point of view
+    features: [Vec<u16>; 2],
+    stm: Color,
+    target: f32,
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn load_positions(file_name: &str, wdl: f32) -> Vec<Position> {
+    let mut positions = vec![];
+    for line in read_to_string(file_name).unwrap().lines() {
+        let (fen, r) = line.split_once('[').unwrap();
+        let (result, score) = r.split_once(']').unwrap();
+        let result = result.parse::<f32>().unwrap();
+        let board = Board::from_fen(fen);
+
+        // results and recorded scores are from white's point of view
+        let score = score.trim().parse::<EvalScore>().unwrap_or_else(|_| {
+            let eval = evaluate(&board);
+            match board.color_to_move {
+                Color::White => eval,
+                Color::Black => -eval,
+            }
+        });
+        let (result, score) = match board.color_to_move {
+            Color::White => (result, score),
+            Color::Black => (1.0 - result, -score),
+        };
+
+        let mut features = [vec![], vec![]];
+        for color in Color::LIST {
+            for piece in Piece::LIST {
+                let mut piece_bb = board.piece_bb(piece, color);
+                bitloop!(|sq| piece_bb, {
+                    for perspective in Color::LIST {
+                        let index = feature_index(perspective, color, piece, sq);
+                        features[perspective.as_index()].push(index as u16);
+                    }
+                });
+            }
+        }
+
+        positions.push(Position {
+            features,
+            stm: board.color_to_move,
+            target: wdl * result + (1.0 - wdl) * sigmoid(score as f32 / SCALE as f32),
+        });
+    }
+
+    positions
+}
+
+// squared clipped ReLU, as in the engine
+fn activate(x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    x * x
+}
+
+// the accumulators of the side to move and the other side, and the output before the sigmoid
+fn forward(params: &[f32], pos: &Position) -> ([[f32; HIDDEN_SIZE]; 2], f32) {
+    let mut accs = [[0.0; HIDDEN_SIZE]; 2];
+    let mut output = params[OUTPUT_BIAS];
+    for (half, perspective) in [pos.stm, pos.stm.flip()].into_iter().enumerate() {
+        let acc = &mut accs[half];
+        acc.copy_from_slice(&params[FEATURE_BIAS..FEATURE_BIAS + HIDDEN_SIZE]);
+        for &feature in &pos.features[perspective.as_index()] {
+            let start = FEATURE_WEIGHTS + usize::from(feature) * HIDDEN_SIZE;
+            for (x, &w) in acc.iter_mut().zip(&params[start..start + HIDDEN_SIZE]) {
+                *x += w;
+            }
+        }
+
+        let start = OUTPUT_WEIGHTS + half * HIDDEN_SIZE;
+        for (&x, &w) in acc.iter().zip(&params[start..start + HIDDEN_SIZE]) {
+            output += activate(x) * w;
+        }
+    }
+
+    (accs, output)
+}
+
+fn loss(params: &[f32], pos: &Position) -> f32 {
+    let error = sigmoid(forward(params, pos).1) - pos.target;
+    error * error
+}
+
+// adds the gradient of the squared error of a single position, and returns the error
+fn update_position_gradient(params: &[f32], pos: &Position, gradient: &mut [f32]) -> f32 {
+    let (accs, output) = forward(params, pos);
+    let prediction = sigmoid(output);
+    let error = prediction - pos.target;
+    let g = 2.0 * error * prediction * (1.0 - prediction);
+
+    gradient[OUTPUT_BIAS] += g;
+    for (half, perspective) in [pos.stm, pos.stm.flip()].into_iter().enumerate() {
+        let mut acc_gradient = [0.0; HIDDEN_SIZE];
+        let start = OUTPUT_WEIGHTS + half * HIDDEN_SIZE;
+        for (i, &x) in accs[half].iter().enumerate() {
+            gradient[start + i] += g * activate(x);
+            if x > 0.0 && x < 1.0 {
+                acc_gradient[i] = g * params[start + i] * 2.0 * x;
+            }
+        }
+
+        for (grad, &x) in gradient[FEATURE_BIAS..FEATURE_BIAS + HIDDEN_SIZE]
+            .iter_mut()
+            .zip(&acc_gradient)
+        {
+            *grad += x;
+        }
+        for &feature in &pos.features[perspective.as_index()] {
+            let start = FEATURE_WEIGHTS + usize::from(feature) * HIDDEN_SIZE;
+            for (grad, &x) in gradient[start..start + HIDDEN_SIZE]
+                .iter_mut()
+                .zip(&acc_gradient)
+            {
+                *grad += x;
+            }
+        }
+    }
+
+    error * error
+}
+
+struct Trainer {
+    params: Vec<f32>,
+    momentum: Vec<f32>,
+    velocity: Vec<f32>,
+    epoch: u32,
+    threads: usize,
+}
+
+impl Trainer {
+    fn new(threads: usize) -> Self {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut params = vec![0.0; PARAM_COUNT];
+
+        // about 32 features are active at once, so scale for that rather than all 768 inputs
+        let feature_range = 1.0 / 32.0_f32.sqrt();
+        for w in &mut params[FEATURE_WEIGHTS..FEATURE_BIAS] {
+            *w = rng.uniform(feature_range);
+        }
+        let output_range = 1.0 / ((2 * HIDDEN_SIZE) as f32).sqrt();
+        for w in &mut params[OUTPUT_WEIGHTS..OUTPUT_BIAS] {
+            *w = rng.uniform(output_range);
+        }
+
+        Self {
+            params,
+            momentum: vec![0.0; PARAM_COUNT],
+            velocity: vec![0.0; PARAM_COUNT],
+            epoch: 0,
+            threads,
+        }
+    }
+
+    // same thread splitting as the HCE tuner: every thread sums the gradient of its chunk
+    fn gradient(&self, positions: &[Position], batch: &[usize]) -> (Vec<f32>, f64) {
+        let size = (batch.len() / self.threads).max(1);
+        thread::scope(|s| {
+            batch
+                .chunks(size)
+                .map(|chunk| {
+                    s.spawn(|| {
+                        let mut chunk_grad = vec![0.0; PARAM_COUNT];
+                        let loss = chunk.iter().fold(0.0, |acc, &i| {
+                            let pos = &positions[i];
+                            acc + f64::from(update_position_gradient(
+                                &self.params,
+                                pos,
+                                &mut chunk_grad,
+                            ))
+                        });
+                        (chunk_grad, loss)
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|p| p.join().unwrap())
+                .fold(
+                    (vec![0.0; PARAM_COUNT], 0.0),
+                    |(mut a, a_loss), (b, b_loss)| {
+                        for (x, y) in a.iter_mut().zip(&b) {
+                            *x += y;
+                        }
+                        (a, a_loss + b_loss)
+                    },
+                )
+        })
+    }
+
+    fn loss(&self, positions: &[Position]) -> f64 {
+        if positions.is_empty() {
+            return 0.0;
+        }
+
+        let size = (positions.len() / self.threads).max(1);
+        thread::scope(|s| {
+            positions
+                .chunks(size)
+                .map(|chunk| {
+                    s.spawn(|| {
+                        chunk
+                            .iter()
+                            .map(|pos| f64::from(loss(&self.params, pos)))
+                            .sum::<f64>()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|p| p.join().unwrap())
+                .sum::<f64>()
+        }) / positions.len() as f64
+    }
+
+    fn adam_step(&mut self, gradient: &[f32], batch_size: usize, lr: f32) {
+        const BETA1: f32 = 0.9;
+        const BETA2: f32 = 0.999;
+        const EPSILON: f32 = 1e-8;
+
+        for i in 0..PARAM_COUNT {
+            let grad = gradient[i] / batch_size as f32;
+            self.momentum[i] = BETA1 * self.momentum[i] + (1.0 - BETA1) * grad;
+            self.velocity[i] = BETA2 * self.velocity[i] + (1.0 - BETA2) * grad * grad;
+            self.params[i] -= lr * self.momentum[i] / (EPSILON + self.velocity[i].sqrt());
+
+            let limit = clip_limit(i);
+            self.params[i] = self.params[i].clamp(-limit, limit);
+        }
+    }
+
+    fn train(
+        &mut self,
+        positions: &[Position],
+        validation: &[Position],
+        options: &Options,
+        output: &str,
+    ) {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15 ^ u64::from(self.epoch));
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+
+        while self.epoch < options.epochs {
+            self.epoch += 1;
+            let lr = options.lr(self.epoch);
+            let stopwatch = Instant::now();
+
+            rng.shuffle(&mut order);
+            let mut train_loss = 0.0;
+            for batch in order.chunks(options.batch_size) {
+                let (gradient, loss) = self.gradient(positions, batch);
+                self.adam_step(&gradient, batch.len(), lr);
+                train_loss += loss;
+            }
+
+            println!("Epoch: {}", self.epoch);
+            println!("Learning rate: {lr}");
+            println!("Train loss: {}", train_loss / positions.len() as f64);
+            println!("Validation loss: {}", self.loss(validation));
+            println!("Time: {:.1}s\n", stopwatch.elapsed().as_secs_f64());
+
+            self.save_net(output);
+            self.save_checkpoint(&format!("{output}.ckpt"));
+        }
+    }
+
+    fn save_net(&self, path: &str) {
+        let mut bytes = Vec::with_capacity(PARAM_COUNT * 2 + 64);
+        for (i, &w) in self.params.iter().enumerate() {
+            let q = (w * quantization(i) as f32).round();
+            let q = q.clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16;
+            bytes.extend_from_slice(&q.to_le_bytes());
+        }
+        while bytes.len() % 64 != 0 {
+            bytes.push(0);
+        }
+
+        let mut file = BufWriter::new(File::create(path).unwrap());
+        file.write_all(&bytes).unwrap();
+    }
+
+    // header: magic, format version, epochs trained, then params, momentum and velocity
+    fn save_checkpoint(&self, path: &str) {
+        let mut file = BufWriter::new(File::create(path).unwrap());
+        file.write_all(&CHECKPOINT_MAGIC).unwrap();
+        file.write_all(&CHECKPOINT_VERSION.to_le_bytes()).unwrap();
+        file.write_all(&self.epoch.to_le_bytes()).unwrap();
+        for values in [&self.params, &self.momentum, &self.velocity] {
+            for x in values {
+                file.write_all(&x.to_le_bytes()).unwrap();
+            }
+        }
+        file.flush().unwrap();
+    }
+
+    fn load_checkpoint(&mut self, path: &str) {
+        let mut file = BufReader::new(File::open(path).unwrap());
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic).unwrap();
+        assert_eq!(magic, CHECKPOINT_MAGIC, "not a checkpoint file");
+
+        let mut data = [0; 4];
+        file.read_exact(&mut data).unwrap();
+        assert_eq!(
+            u32::from_le_bytes(data),
+            CHECKPOINT_VERSION,
+            "unsupported checkpoint version"
+        );
+        file.read_exact(&mut data).unwrap();
+        self.epoch = u32::from_le_bytes(data);
+
+        for values in [&mut self.params, &mut self.momentum, &mut self.velocity] {
+            for x in values.iter_mut() {
+                file.read_exact(&mut data).unwrap();
+                *x = f32::from_le_bytes(data);
+            }
+        }
+    }
+}
+
+// keeps the quantized net from overflowing the engine's accumulator and output sums
+fn clip_limit(i: usize) -> f32 {
+    if i < OUTPUT_WEIGHTS {
+        MAX_FEATURE_WEIGHT
+    } else if i < OUTPUT_BIAS {
+        MAX_OUTPUT_WEIGHT as f32 / QB as f32
+    } else {
+        f32::from(i16::MAX) / (QA * QB) as f32
+    }
+}
+
+// the engine's quantization factor of the parameter at this index
+fn quantization(i: usize) -> i32 {
+    if i < OUTPUT_WEIGHTS {
+        QA
+    } else if i < OUTPUT_BIAS {
+        QB
+    } else {
+        QA * QB
+    }
+}
+
+// xorshift, enough for shuffling and initialization
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn uniform(&mut self, range: f32) -> f32 {
+        let unit = (self.next() >> 40) as f32 / (1u64 << 24) as f32;
+        (2.0 * unit - 1.0) * range
+    }
+
+    fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            values.swap(i, j);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let file_name: &str = args.get(1).expect("expected data file to read!");
+    let output: &str = args.get(2).expect("expected output net file!");
+    let options = Options::parse(&args[3..]);
+
+    let mut positions = load_positions(file_name, options.wdl);
+    // positions of the same game are alike, so shuffle before holding any out
+    Rng(0x853c_49e6_748f_ea9b).shuffle(&mut positions);
+    let validation_count = (positions.len() as f32 * options.validation) as usize;
+    let validation = positions.split_off(positions.len() - validation_count);
+    println!(
+        "{} positions loaded, {} held out for validation\n",
+        positions.len() + validation.len(),
+        validation.len()
+    );
+
+    let mut trainer = Trainer::new(options.threads);
+    if let Some(path) = &options.resume {
+        trainer.load_checkpoint(path);
+        println!("resuming after epoch {}\n", trainer.epoch);
+    }
+    trainer.train(&positions, &validation, &options, output);
+}
@@ -0,0 +1,215 @@
+use engine::{
+    board::board_representation::{Board, Color, START_FEN},
+    board::chess_move::Move,
+    board::movegen::MoveGenerator,
+    board::zobrist::ZobristHash,
+    board::zobrist_stack::ZobristStack,
+    create_thread_data,
+    eval::evaluation::EvalScore,
+    search::history_table::History,
+    search::search::{write_stop_flag, SearchLimit, SearchOptions, SearchResults, Searcher},
+    search::skill::{splitmix64, Skill},
+    search::thread_data::Nodes,
+    search::transposition_table::TranspositionTable,
+    tablebase::probe::Syzygy,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Plays games between two fixed-strength settings of the engine and reports the score and
+// Elo difference of the first. A player is either "level=N", a skill level that uses its own
+// node limit, or "nodes=N", full strength at N nodes per move. Each random opening is played
+// with both colors.
+//
+// usage: skill_match <player> <player> [game pairs]
+
+#[derive(Debug, Copy, Clone)]
+enum Player {
+    Level(u8),
+    Nodes(Nodes),
+}
+
+impl Player {
+    fn parse(arg: &str) -> Self {
+        let (kind, value) = arg.split_once('=').expect("expected level=N or nodes=N");
+        match kind {
+            "level" => Self::Level(value.parse().unwrap()),
+            "nodes" => Self::Nodes(value.parse().unwrap()),
+            _ => panic!("unknown player {arg}"),
+        }
+    }
+
+    fn search(
+        self,
+        board: &Board,
+        zobrist_stack: &ZobristStack,
+        history: &mut History,
+        tt: &TranspositionTable,
+    ) -> SearchResults {
+        let (limits, skill) = match self {
+            Self::Level(level) => (vec![], Skill::from_level(level)),
+            Self::Nodes(nodes) => (vec![SearchLimit::Nodes(nodes)], Skill::default()),
+        };
+        let options = SearchOptions {
+            skill,
+            ..SearchOptions::default()
+        };
+
+        create_thread_data!(thread_data);
+        write_stop_flag(false);
+
+        let mut searcher = Searcher::new(
+            limits,
+            zobrist_stack,
+            history,
+            tt,
+            Syzygy::new(),
+            thread_data,
+            options,
+        );
+        let results = searcher.go::<true>(board, false);
+        searcher.search_complete_actions(history);
+        results
+    }
+}
+
+const OPENING_PLY: usize = 8;
+const MAX_PLY: usize = 400;
+// both sides agree that the game is decided
+const ADJUDICATION_SCORE: EvalScore = 1000;
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut generator = MoveGenerator::new();
+    let mut moves = vec![];
+    while let Some(mv) = generator.simple_next::<true>(board) {
+        if board.clone().simple_try_play_move(mv) {
+            moves.push(mv);
+        }
+    }
+    moves
+}
+
+fn play(board: &mut Board, zobrist_stack: &mut ZobristStack, mv: Move) {
+    let hash_base = ZobristHash::incremental_update_base(board);
+    assert!(board.try_play_move(mv, zobrist_stack, hash_base));
+}
+
+fn random_opening(seed: &mut u64) -> Vec<Move> {
+    loop {
+        let mut board = Board::from_fen(START_FEN);
+        let mut zobrist_stack = ZobristStack::new(&board);
+        let mut opening = vec![];
+        for _ in 0..OPENING_PLY {
+            let moves = legal_moves(&board);
+            if moves.is_empty() {
+                break;
+            }
+            *seed = splitmix64(*seed);
+            let mv = moves[(*seed % moves.len() as u64) as usize];
+            play(&mut board, &mut zobrist_stack, mv);
+            opening.push(mv);
+        }
+
+        if opening.len() == OPENING_PLY && !MoveGenerator::no_legal_moves(&board) {
+            return opening;
+        }
+    }
+}
+
+// the result for white: 1.0, 0.5 or 0.0
+fn play_game(white: Player, black: Player, opening: &[Move]) -> f64 {
+    let mut board = Board::from_fen(START_FEN);
+    let mut zobrist_stack = ZobristStack::new(&board);
+    for &mv in opening {
+        play(&mut board, &mut zobrist_stack, mv);
+    }
+
+    let mut histories = [History::new(), History::new()];
+    let tts = [
+        TranspositionTable::new(16, 1),
+        TranspositionTable::new(16, 1),
+    ];
+    let mut last_scores = [0; 2];
+    for _ in 0..MAX_PLY {
+        if MoveGenerator::no_legal_moves(&board) {
+            return match (board.in_check(), board.color_to_move) {
+                (false, _) => 0.5,
+                (true, Color::White) => 0.0,
+                (true, Color::Black) => 1.0,
+            };
+        }
+        if zobrist_stack.twofold_repetition(board.halfmoves)
+            || board.insufficient_material_draw()
+            || board.fifty_move_draw()
+        {
+            return 0.5;
+        }
+
+        let color = board.color_to_move;
+        let player = match color {
+            Color::White => white,
+            Color::Black => black,
+        };
+        let i = color.as_index();
+        let SearchResults { best_move, score } =
+            player.search(&board, &zobrist_stack, &mut histories[i], &tts[i]);
+
+        last_scores[i] = match color {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        if last_scores.iter().all(|&s| s >= ADJUDICATION_SCORE) {
+            return 1.0;
+        } else if last_scores.iter().all(|&s| s <= -ADJUDICATION_SCORE) {
+            return 0.0;
+        }
+
+        play(&mut board, &mut zobrist_stack, best_move);
+    }
+
+    0.5
+}
+
+fn elo(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let first = Player::parse(args.get(1).expect("expected two players!"));
+    let second = Player::parse(args.get(2).expect("expected two players!"));
+    let pairs: usize = args.get(3).map_or(20, |x| x.parse().unwrap_or(20));
+
+    let mut seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+
+    // wins, draws and losses of the first player
+    let mut wdl = [0; 3];
+    let mut results = vec![];
+    for pair in 1..=pairs {
+        let opening = random_opening(&mut seed);
+        for first_is_white in [true, false] {
+            let result = if first_is_white {
+                play_game(first, second, &opening)
+            } else {
+                1.0 - play_game(second, first, &opening)
+            };
+            results.push(result);
+            wdl[(2.0 - 2.0 * result) as usize] += 1;
+        }
+
+        let n = results.len() as f64;
+        let score = results.iter().sum::<f64>() / n;
+        let variance = results.iter().map(|r| (r - score).powi(2)).sum::<f64>() / n;
+        let error = 1.96 * (variance / n).sqrt();
+        println!(
+            "{pair}/{pairs} pairs: +{} ={} -{}, score {score:.3}, elo {:.0} +- {:.0}",
+            wdl[0],
+            wdl[1],
+            wdl[2],
+            elo(score),
+            (elo(score + error) - elo(score - error)) / 2.0,
+        );
+    }
+}
@@ -10,7 +10,13 @@ fn main() {
         .get(2)
         .map_or_else(|| 1, |x| x.parse::<usize>().unwrap_or(1));
 
-    let mut tuner = tuning::Tuner::new(threads);
+    // eval_constants.rs by default, or a file for the EvalParamsFile option
+    let output = match args.get(3).map(String::as_str) {
+        Some("params") => tuning::Output::Params,
+        _ => tuning::Output::Constants,
+    };
+
+    let mut tuner = tuning::Tuner::new(threads, output);
     tuner.load_from_file(file_name);
     tuner.train();
 }
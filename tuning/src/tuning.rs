@@ -3,7 +3,9 @@ use engine::{
     board::board_representation::{
         Board, Color, Piece, Square, NUM_COLORS, NUM_RANKS, NUM_SQUARES,
     },
-    eval::evaluation::{phase, trace_of_position, Phase, PHASE_MAX},
+    eval::endgame::{scale_factor, Endgame, SCALE_NORMAL},
+    eval::eval_params::EvalParams,
+    eval::evaluation::{phase, trace_of_position, Phase, ScoreTuple, PHASE_MAX},
     eval::{
         evaluation::{KINGSIDE_INDEX, QUEENSIDE_INDEX, SAFETY_LIMIT},
         trace::{
@@ -67,20 +69,26 @@ struct Entry {
     feature_vec: Vec<Feature>,
     safety_feature_vec: [Vec<Feature>; NUM_COLORS as usize],
     phase: Phase,
+    // the share of the endgame part the eval keeps for each side, when that side is ahead
+    scale: [f64; NUM_COLORS as usize],
     game_result: f64,
 }
 
 impl Entry {
     fn new(board: &Board, game_result: f64) -> Self {
+        let phase = phase(board);
         let mut entry = Self {
             feature_vec: vec![],
             safety_feature_vec: [vec![], vec![]],
-            phase: phase(board),
+            phase,
+            scale: Color::LIST.map(|strong| {
+                f64::from(scale_factor(board, strong, phase)) / f64::from(SCALE_NORMAL)
+            }),
             game_result,
         };
 
         let trace = trace_of_position(board);
-        for (i, &value) in trace.linear.iter().enumerate() {
+        for (i, &value) in trace.linear_diff().iter().enumerate() {
             if value != 0 {
                 entry.feature_vec.push(Feature::new(value, i));
             }
@@ -113,6 +121,11 @@ impl Entry {
     }
 
     fn evaluation(&self, weights: &TunerStruct) -> f64 {
+        self.taper(self.score(weights))
+    }
+
+    // the mg and eg parts of the eval
+    fn score(&self, weights: &TunerStruct) -> S {
         let mut score = S::new(0.0, 0.0);
 
         for feature in &self.feature_vec {
@@ -124,7 +137,12 @@ impl Entry {
         score +=
             (0.01 * w_ap.max(0.0).square()).min(limit) - (0.01 * b_ap.max(0.0).square()).min(limit);
 
-        (score.mg() * self.mg_phase() + score.eg() * self.eg_phase()) / f64::from(PHASE_MAX)
+        score
+    }
+
+    fn taper(&self, score: S) -> f64 {
+        (score.mg() * self.mg_phase() + score.eg() * self.eg_phase(score.eg()))
+            / f64::from(PHASE_MAX)
     }
 
     fn error(&self, weights: &TunerStruct) -> f64 {
@@ -137,12 +155,28 @@ impl Entry {
         f64::from(self.phase)
     }
 
-    fn eg_phase(&self) -> f64 {
-        f64::from(PHASE_MAX - self.phase)
+    // scaled for whichever side the endgame part favors, as in the eval
+    fn eg_phase(&self, eg: f64) -> f64 {
+        let strong = if eg >= 0.0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        f64::from(PHASE_MAX - self.phase) * self.scale[strong.as_index()]
     }
 }
 
+// what the tuner writes whenever it checks its progress
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    // eval_constants.rs, to be pasted into the engine
+    Constants,
+    // eval_params.txt, for the EvalParamsFile option
+    Params,
+}
+
 pub struct Tuner {
+    output: Output,
     entries: Vec<Vec<Entry>>,
     gradient: TunerStruct,
     weights: TunerStruct,
@@ -201,8 +235,9 @@ impl Tuner {
         result
     }
 
-    pub fn new(threads: usize) -> Self {
+    pub fn new(threads: usize, output: Output) -> Self {
         Self {
+            output,
             entries: vec![],
             gradient: TunerStruct::new(),
             weights: Self::new_weights(),
@@ -222,6 +257,10 @@ impl Tuner {
             let game_result = r.split_once(']').unwrap().0.parse::<f64>().unwrap();
 
             let board = Board::from_fen(fen);
+            // the eval leaves these to the specialized endgame evals, so they say nothing of it
+            if Endgame::probe(&board).is_some() {
+                continue;
+            }
             batch.push(Entry::new(&board, game_result));
             entry_count += 1;
 
@@ -259,13 +298,14 @@ impl Tuner {
         weights: &TunerStruct,
     ) {
         let r = entry.game_result;
-        let eval = entry.evaluation(weights);
+        let score = entry.score(weights);
+        let eval = entry.taper(score);
         let sigmoid = Self::sigmoid(eval);
         let sigmoid_prime = Self::sigmoid_prime(sigmoid);
 
         let coeff = S::new(
             ((r - sigmoid) * sigmoid_prime * entry.mg_phase()) / f64::from(PHASE_MAX),
-            ((r - sigmoid) * sigmoid_prime * entry.eg_phase()) / f64::from(PHASE_MAX),
+            ((r - sigmoid) * sigmoid_prime * entry.eg_phase(score.eg())) / f64::from(PHASE_MAX),
         );
 
         for feature in &entry.feature_vec {
@@ -359,7 +399,10 @@ impl Tuner {
                 println!("MSE: {mse}");
                 println!("MSE change since previous: {delta_mse}\n");
 
-                self.create_output_file();
+                match self.output {
+                    Output::Constants => self.create_output_file(),
+                    Output::Params => self.create_params_file(),
+                }
 
                 if delta_mse < Self::CONVERGENCE_DELTA {
                     return;
@@ -649,6 +692,15 @@ impl Tuner {
         .unwrap();
     }
 
+    fn create_params_file(&self) {
+        let round = |w: &S| ScoreTuple::new(w.mg() as i32, w.eg() as i32);
+        let linear = self.weights.linear.iter().map(round).collect::<Vec<_>>();
+        let safety = self.weights.safety.iter().map(round).collect::<Vec<_>>();
+
+        let mut output = BufWriter::new(File::create("eval_params.txt").unwrap());
+        write!(output, "{}", EvalParams::from_weights(&linear, &safety)).unwrap();
+    }
+
     fn create_output_file(&self) {
         let mut output = BufWriter::new(File::create("eval_constants.rs").unwrap());
         self.write_header(&mut output);
@@ -1,11 +1,32 @@
-use engine::search::bench;
+use engine::search::{bench, mate, search_tree};
 mod uci;
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    for arg in args {
+    for (i, arg) in args.iter().enumerate() {
+        // usage: bench [evalcache]
         if arg == "bench" {
-            bench::bench();
+            if args.get(i + 1).is_some_and(|x| x == "evalcache") {
+                bench::bench_eval_cache();
+            } else {
+                bench::bench();
+            }
+            return;
+        }
+
+        // usage: mate <EPD file> [node limit per position]
+        if arg == "mate" {
+            let file_name = args.get(i + 1).expect("expected EPD file to read!");
+            let node_limit = args
+                .get(i + 2)
+                .map_or(u64::MAX, |x| x.parse::<u64>().unwrap_or(u64::MAX));
+            mate::solve_epd(file_name, 64, node_limit);
+            return;
+        }
+
+        // usage: tree <tree file> [node index] [moves...]
+        if arg == "tree" {
+            search_tree::query(&args[i + 1..]);
             return;
         }
     }
@@ -4,12 +4,19 @@ use engine::{
     board::zobrist::ZobristHash,
     board::zobrist_stack::ZobristStack,
     create_thread_data,
-    eval::evaluation::evaluate,
+    eval::eval_params::{self, EvalParams},
+    eval::eval_table::EvalTable,
+    eval::evaluation::{evaluate, EvalScore},
+    eval::nnue::{self, Network, NNUE_ENABLED},
     search::history_table::History,
-    search::search::{self, Depth, SearchLimit, Searcher},
+    search::mate::MateSolver,
+    search::search::{self, Depth, Ply, SearchLimit, SearchOptions, Searcher},
+    search::search_tree::TREE_ENABLED,
+    search::skill::Skill,
     search::thread_data::Nodes,
     search::time_management::{Milliseconds, TimeArgs, TimeManager},
     search::transposition_table::TranspositionTable,
+    search::tunables::{self, TUNABLES, TUNING_ENABLED},
     tablebase::probe::{Syzygy, FATHOM_IS_COMPILED},
 };
 
@@ -25,9 +32,32 @@ enum UciCommand {
     SetOptionHash(usize),
     SetOptionThreads(usize),
     SetOptionSyzygyPath(String),
+    SetOptionHashFile(String),
+    SetOptionShowWdl(bool),
+    SetOptionNormalizeEval(bool),
+    SetOptionTunable(String, i32),
+    SetOptionContempt(EvalScore),
+    SetOptionAutoContempt(bool),
+    SetOptionOpponent(String),
+    SetOptionRatingAdv(i32),
+    SetOptionLimitStrength(bool),
+    SetOptionElo(i32),
+    SetOptionSkillLevel(u8),
+    SetOptionNodesTime(Nodes),
+    SetOptionDeterministic(bool),
+    SetOptionEvalCache(bool),
+    SetOptionQSearchChecks(bool),
+    SetOptionEvalFile(String),
+    SetOptionEvalParamsFile(String),
+    SetOptionTreeFile(String),
+    SetOptionTreePly(Ply),
+    SetOptionTreeNodes(usize),
+    SaveHash,
+    LoadHash,
 
     // User Commands
     StaticEval,
+    SpsaInput,
 }
 
 pub struct UciHandler {
@@ -39,6 +69,16 @@ pub struct UciHandler {
     stored_message: Option<String>,
     num_threads: usize,
     tablebase: Syzygy,
+    hash_file: String,
+    search_options: SearchOptions,
+    contempt: EvalScore,
+    auto_contempt: bool,
+    rating_adv: Option<i32>,
+    limit_strength: bool,
+    elo: i32,
+    skill_level: u8,
+    nodes_time: Nodes,
+    tree_file: String,
 }
 
 macro_rules! send_uci_option {
@@ -68,12 +108,74 @@ impl UciHandler {
     const HASH_MIN: usize = 0;
     const HASH_MAX: usize = 8192;
 
+    // the mate solver's own table, allocated for each "go mate"
+    const MATE_HASH: usize = 64;
+
     const THREADS_DEFAULT: usize = 1;
     const THREADS_MIN: usize = 1;
     const THREADS_MAX: usize = 64;
 
     const SYZYGY_PATH_DEFAULT: &str = "<empty>";
 
+    const HASH_FILE_DEFAULT: &str = "<empty>";
+
+    const SHOW_WDL_DEFAULT: bool = false;
+
+    const NORMALIZE_EVAL_DEFAULT: bool = false;
+
+    const CONTEMPT_DEFAULT: EvalScore = 0;
+    const CONTEMPT_MIN: EvalScore = -100;
+    const CONTEMPT_MAX: EvalScore = 100;
+
+    const AUTO_CONTEMPT_DEFAULT: bool = false;
+    const AUTO_CONTEMPT_ELO_PER_CP: i32 = 20;
+    const AUTO_CONTEMPT_MAX: EvalScore = 50;
+
+    const RATING_ADV_DEFAULT: i32 = 0;
+    const RATING_ADV_MIN: i32 = -4000;
+    const RATING_ADV_MAX: i32 = 4000;
+
+    const OPPONENT_DEFAULT: &str = "<empty>";
+
+    const LIMIT_STRENGTH_DEFAULT: bool = false;
+
+    const ELO_DEFAULT: i32 = Skill::MAX_ELO;
+    const ELO_MIN: i32 = Skill::MIN_ELO;
+    const ELO_MAX: i32 = Skill::MAX_ELO;
+
+    const SKILL_LEVEL_DEFAULT: u8 = Skill::MAX_LEVEL;
+    const SKILL_LEVEL_MIN: u8 = 0;
+    const SKILL_LEVEL_MAX: u8 = Skill::MAX_LEVEL;
+
+    // nodes per millisecond, 0 to use real time
+    const NODES_TIME_DEFAULT: Nodes = 0;
+    const NODES_TIME_MIN: Nodes = 0;
+    const NODES_TIME_MAX: Nodes = 10000;
+
+    const DETERMINISTIC_DEFAULT: bool = false;
+    // the node clock used in deterministic mode when nodestime isn't set
+    const DETERMINISTIC_NODES_TIME: Nodes = 1000;
+
+    const EVAL_CACHE_DEFAULT: bool = true;
+    const QSEARCH_CHECKS_DEFAULT: bool = true;
+
+    // only offered when built with the "nnue" feature, empty to use the embedded net if any
+    const EVAL_FILE_DEFAULT: &str = "<empty>";
+    // empty to use the compiled eval constants
+    const EVAL_PARAMS_FILE_DEFAULT: &str = "<empty>";
+
+    // search tree recording, only offered when built with the "search-tree" feature
+    const TREE_FILE_DEFAULT: &str = "<empty>";
+    const TREE_PLY_DEFAULT: Ply = 4;
+    const TREE_PLY_MIN: Ply = 1;
+    const TREE_PLY_MAX: Ply = 64;
+    const TREE_NODES_DEFAULT: usize = 1_000_000;
+    const TREE_NODES_MIN: usize = 1;
+    const TREE_NODES_MAX: usize = 100_000_000;
+
+    // used to work out the rating advantage from UCI_Opponent (CCRL blitz)
+    const ESTIMATED_ELO: i32 = 3082;
+
     pub fn new() -> Self {
         let board = Board::from_fen(START_FEN);
         let zobrist_stack = ZobristStack::new(&board);
@@ -81,11 +183,32 @@ impl UciHandler {
             board,
             zobrist_stack,
             history: History::new(),
-            tt: TranspositionTable::new(Self::HASH_DEFAULT),
+            tt: TranspositionTable::new(Self::HASH_DEFAULT, Self::THREADS_DEFAULT),
             time_manager: TimeManager::new(Self::OVERHEAD_DEFAULT),
             stored_message: None,
             num_threads: Self::THREADS_DEFAULT,
             tablebase: Syzygy::new(),
+            hash_file: Self::HASH_FILE_DEFAULT.to_owned(),
+            search_options: SearchOptions {
+                show_wdl: Self::SHOW_WDL_DEFAULT,
+                normalize_eval: Self::NORMALIZE_EVAL_DEFAULT,
+                contempt: Self::CONTEMPT_DEFAULT,
+                skill: Skill::from_level(Self::SKILL_LEVEL_DEFAULT),
+                nodes_time: Self::NODES_TIME_DEFAULT,
+                deterministic: Self::DETERMINISTIC_DEFAULT,
+                no_eval_cache: !Self::EVAL_CACHE_DEFAULT,
+                no_qsearch_checks: !Self::QSEARCH_CHECKS_DEFAULT,
+                tree_ply: Self::TREE_PLY_DEFAULT,
+                tree_nodes: Self::TREE_NODES_DEFAULT,
+            },
+            contempt: Self::CONTEMPT_DEFAULT,
+            auto_contempt: Self::AUTO_CONTEMPT_DEFAULT,
+            rating_adv: None,
+            limit_strength: Self::LIMIT_STRENGTH_DEFAULT,
+            elo: Self::ELO_DEFAULT,
+            skill_level: Self::SKILL_LEVEL_DEFAULT,
+            nodes_time: Self::NODES_TIME_DEFAULT,
+            tree_file: Self::TREE_FILE_DEFAULT.to_owned(),
         }
     }
 
@@ -147,10 +270,16 @@ impl UciHandler {
                     self.process_command(UciCommand::Go(arg_vec));
                 }
                 "setoption" => {
-                    let name = message[2];
-                    let val = message[4];
-
-                    match name {
+                    // option names may contain spaces, e.g. "Skill Level"
+                    let value_index = message
+                        .iter()
+                        .position(|&token| token == "value")
+                        .unwrap_or(message.len());
+                    let name = message[2.min(value_index)..value_index].join(" ");
+                    let value = message.get(value_index + 1..).unwrap_or(&[]).join(" ");
+                    let val = value.as_str();
+
+                    match name.as_str() {
                         "Overhead" => self.process_command(UciCommand::SetOptionOverhead(
                             val.parse::<Milliseconds>()
                                 .unwrap_or(Self::OVERHEAD_DEFAULT),
@@ -162,13 +291,85 @@ impl UciHandler {
                             val.parse::<usize>().unwrap_or(Self::THREADS_DEFAULT),
                         )),
                         "SyzygyPath" => {
-                            let path = &message[4..].join(" ");
-                            self.process_command(UciCommand::SetOptionSyzygyPath(path.to_owned()))
+                            self.process_command(UciCommand::SetOptionSyzygyPath(val.to_owned()))
+                        }
+                        "HashFile" => {
+                            self.process_command(UciCommand::SetOptionHashFile(val.to_owned()))
+                        }
+                        "UCI_ShowWDL" => self.process_command(UciCommand::SetOptionShowWdl(
+                            val.parse::<bool>().unwrap_or(Self::SHOW_WDL_DEFAULT),
+                        )),
+                        "NormalizeEval" => {
+                            self.process_command(UciCommand::SetOptionNormalizeEval(
+                                val.parse::<bool>().unwrap_or(Self::NORMALIZE_EVAL_DEFAULT),
+                            ));
+                        }
+                        "Contempt" => self.process_command(UciCommand::SetOptionContempt(
+                            val.parse::<EvalScore>().unwrap_or(Self::CONTEMPT_DEFAULT),
+                        )),
+                        "AutoContempt" => {
+                            self.process_command(UciCommand::SetOptionAutoContempt(
+                                val.parse::<bool>().unwrap_or(Self::AUTO_CONTEMPT_DEFAULT),
+                            ));
+                        }
+                        "UCI_Opponent" => {
+                            self.process_command(UciCommand::SetOptionOpponent(val.to_owned()));
+                        }
+                        "UCI_RatingAdv" => self.process_command(UciCommand::SetOptionRatingAdv(
+                            val.parse::<i32>().unwrap_or(Self::RATING_ADV_DEFAULT),
+                        )),
+                        "UCI_LimitStrength" => {
+                            self.process_command(UciCommand::SetOptionLimitStrength(
+                                val.parse::<bool>().unwrap_or(Self::LIMIT_STRENGTH_DEFAULT),
+                            ));
+                        }
+                        "UCI_Elo" => self.process_command(UciCommand::SetOptionElo(
+                            val.parse::<i32>().unwrap_or(Self::ELO_DEFAULT),
+                        )),
+                        "Skill Level" => self.process_command(UciCommand::SetOptionSkillLevel(
+                            val.parse::<u8>().unwrap_or(Self::SKILL_LEVEL_DEFAULT),
+                        )),
+                        "nodestime" => self.process_command(UciCommand::SetOptionNodesTime(
+                            val.parse::<Nodes>().unwrap_or(Self::NODES_TIME_DEFAULT),
+                        )),
+                        "Deterministic" => {
+                            self.process_command(UciCommand::SetOptionDeterministic(
+                                val.parse::<bool>().unwrap_or(Self::DETERMINISTIC_DEFAULT),
+                            ));
+                        }
+                        "EvalCache" => self.process_command(UciCommand::SetOptionEvalCache(
+                            val.parse::<bool>().unwrap_or(Self::EVAL_CACHE_DEFAULT),
+                        )),
+                        "QSearchChecks" => {
+                            self.process_command(UciCommand::SetOptionQSearchChecks(
+                                val.parse::<bool>().unwrap_or(Self::QSEARCH_CHECKS_DEFAULT),
+                            ));
+                        }
+                        "EvalFile" => {
+                            self.process_command(UciCommand::SetOptionEvalFile(val.to_owned()));
+                        }
+                        "EvalParamsFile" => self
+                            .process_command(UciCommand::SetOptionEvalParamsFile(val.to_owned())),
+                        "TreeFile" => {
+                            self.process_command(UciCommand::SetOptionTreeFile(val.to_owned()));
+                        }
+                        "TreePly" => self.process_command(UciCommand::SetOptionTreePly(
+                            val.parse::<Ply>().unwrap_or(Self::TREE_PLY_DEFAULT),
+                        )),
+                        "TreeNodes" => self.process_command(UciCommand::SetOptionTreeNodes(
+                            val.parse::<usize>().unwrap_or(Self::TREE_NODES_DEFAULT),
+                        )),
+                        _ => {
+                            if let Ok(val) = val.parse::<i32>() {
+                                self.process_command(UciCommand::SetOptionTunable(name, val));
+                            }
                         }
-                        _ => (),
                     }
                 }
+                "savehash" => self.process_command(UciCommand::SaveHash),
+                "loadhash" => self.process_command(UciCommand::LoadHash),
                 "eval" => self.process_command(UciCommand::StaticEval),
+                "spsa" => self.process_command(UciCommand::SpsaInput),
                 "quit" => kill_program(),
                 _ => (),
             }
@@ -213,13 +414,135 @@ impl UciHandler {
                         Self::SYZYGY_PATH_DEFAULT
                     );
                 }
+                send_uci_option!("HashFile", "string", "default {}", Self::HASH_FILE_DEFAULT);
+                send_uci_option!("UCI_ShowWDL", "check", "default {}", Self::SHOW_WDL_DEFAULT);
+                send_uci_option!(
+                    "NormalizeEval",
+                    "check",
+                    "default {}",
+                    Self::NORMALIZE_EVAL_DEFAULT
+                );
+                send_uci_option!(
+                    "Contempt",
+                    "spin",
+                    "default {} min {} max {}",
+                    Self::CONTEMPT_DEFAULT,
+                    Self::CONTEMPT_MIN,
+                    Self::CONTEMPT_MAX
+                );
+                send_uci_option!(
+                    "AutoContempt",
+                    "check",
+                    "default {}",
+                    Self::AUTO_CONTEMPT_DEFAULT
+                );
+                send_uci_option!(
+                    "UCI_Opponent",
+                    "string",
+                    "default {}",
+                    Self::OPPONENT_DEFAULT
+                );
+                send_uci_option!(
+                    "UCI_RatingAdv",
+                    "spin",
+                    "default {} min {} max {}",
+                    Self::RATING_ADV_DEFAULT,
+                    Self::RATING_ADV_MIN,
+                    Self::RATING_ADV_MAX
+                );
+                send_uci_option!(
+                    "UCI_LimitStrength",
+                    "check",
+                    "default {}",
+                    Self::LIMIT_STRENGTH_DEFAULT
+                );
+                send_uci_option!(
+                    "UCI_Elo",
+                    "spin",
+                    "default {} min {} max {}",
+                    Self::ELO_DEFAULT,
+                    Self::ELO_MIN,
+                    Self::ELO_MAX
+                );
+                send_uci_option!(
+                    "Skill Level",
+                    "spin",
+                    "default {} min {} max {}",
+                    Self::SKILL_LEVEL_DEFAULT,
+                    Self::SKILL_LEVEL_MIN,
+                    Self::SKILL_LEVEL_MAX
+                );
+                send_uci_option!(
+                    "nodestime",
+                    "spin",
+                    "default {} min {} max {}",
+                    Self::NODES_TIME_DEFAULT,
+                    Self::NODES_TIME_MIN,
+                    Self::NODES_TIME_MAX
+                );
+                send_uci_option!(
+                    "Deterministic",
+                    "check",
+                    "default {}",
+                    Self::DETERMINISTIC_DEFAULT
+                );
+                send_uci_option!("EvalCache", "check", "default {}", Self::EVAL_CACHE_DEFAULT);
+                send_uci_option!(
+                    "QSearchChecks",
+                    "check",
+                    "default {}",
+                    Self::QSEARCH_CHECKS_DEFAULT
+                );
+                send_uci_option!(
+                    "EvalParamsFile",
+                    "string",
+                    "default {}",
+                    Self::EVAL_PARAMS_FILE_DEFAULT
+                );
+                if NNUE_ENABLED {
+                    send_uci_option!("EvalFile", "string", "default {}", Self::EVAL_FILE_DEFAULT);
+                }
+                if TREE_ENABLED {
+                    send_uci_option!("TreeFile", "string", "default {}", Self::TREE_FILE_DEFAULT);
+                    send_uci_option!(
+                        "TreePly",
+                        "spin",
+                        "default {} min {} max {}",
+                        Self::TREE_PLY_DEFAULT,
+                        Self::TREE_PLY_MIN,
+                        Self::TREE_PLY_MAX
+                    );
+                    send_uci_option!(
+                        "TreeNodes",
+                        "spin",
+                        "default {} min {} max {}",
+                        Self::TREE_NODES_DEFAULT,
+                        Self::TREE_NODES_MIN,
+                        Self::TREE_NODES_MAX
+                    );
+                }
+                if TUNING_ENABLED {
+                    for tunable in TUNABLES {
+                        send_uci_option!(
+                            tunable.name,
+                            "spin",
+                            "default {} min {} max {}",
+                            tunable.default,
+                            tunable.min,
+                            tunable.max
+                        );
+                    }
+                }
 
+                // the table allocated at startup
+                self.print_large_pages();
                 println!("uciok");
             }
             UciCommand::IsReady => println!("readyok"),
             UciCommand::UciNewGame => {
                 self.history = History::new();
-                self.tt.reset();
+                self.tt.reset(self.num_threads);
+                self.time_manager.new_game();
             }
             UciCommand::Position(fen, move_vec) => {
                 let mut new_board = Board::from_fen(fen.as_str());
@@ -236,10 +559,17 @@ impl UciHandler {
 
                 self.board = new_board;
                 self.zobrist_stack = new_zobrist_stack;
+
+                // each search then only depends on its own position
+                if self.search_options.deterministic {
+                    self.history = History::new();
+                    self.tt.reset(self.num_threads);
+                }
             }
             UciCommand::Go(arg_vec) => {
                 let mut time_args = TimeArgs::default();
                 let mut search_limits = vec![];
+                let mut mate_moves = None;
 
                 let mut args_iterator = arg_vec.iter();
                 while let Some(arg) = args_iterator.next() {
@@ -297,21 +627,49 @@ impl UciHandler {
                                 search_limits.push(SearchLimit::Nodes(nodes));
                             }
                         }
+                        "mate" => {
+                            mate_moves = args_iterator.next().unwrap().parse::<u8>().ok();
+                        }
                         _ => (),
                     }
                 }
 
+                // solver mode: only a proven mate is reported, and other limits are ignored
+                if let Some(moves) = mate_moves {
+                    let mut solver = MateSolver::new(Self::MATE_HASH, &self.zobrist_stack);
+
+                    search::write_stop_flag(false);
+                    thread::scope(|s| {
+                        s.spawn(|| {
+                            solver.go(&self.board, moves, true);
+                        });
+
+                        Self::handle_stop_and_quit(&mut self.stored_message);
+                    });
+                    return;
+                }
+
+                let color = self.board.color_to_move;
+                let time_args = self.time_manager.node_clock(time_args, color);
                 if time_args != TimeArgs::default() {
                     search_limits.push(SearchLimit::Time(
-                        self.time_manager
-                            .calculate_search_time(time_args, self.board.color_to_move),
+                        self.time_manager.calculate_search_time(time_args, color),
                     ));
                 }
 
-                create_thread_data!(thread_data, self.num_threads);
+                // a weakened search relies on its node limit, so helper threads would only add
+                // strength, and deterministic mode can't have threads racing each other
+                let num_threads = if self.search_options.skill.is_enabled()
+                    || self.search_options.deterministic
+                {
+                    1
+                } else {
+                    self.num_threads
+                };
+                create_thread_data!(thread_data, num_threads);
 
                 let mut secondary_searchers = vec![];
-                for id in 1..self.num_threads {
+                for id in 1..num_threads {
                     secondary_searchers.push(Searcher::new(
                         search_limits.clone(),
                         &self.zobrist_stack,
@@ -319,6 +677,7 @@ impl UciHandler {
                         &self.tt,
                         self.tablebase,
                         thread_data.get_secondary(id),
+                        self.search_options,
                     ));
                 }
 
@@ -329,32 +688,59 @@ impl UciHandler {
                     &self.tt,
                     self.tablebase,
                     thread_data,
+                    self.search_options,
                 );
 
                 search::write_stop_flag(false);
                 thread::scope(|s| {
+                    let helpers = secondary_searchers
+                        .iter_mut()
+                        .map(|searcher| {
+                            s.spawn(|| {
+                                searcher.go::<false>(&self.board, false);
+                                searcher.stats().clone()
+                            })
+                        })
+                        .collect::<Vec<_>>();
+
                     s.spawn(|| {
                         primary_searcher.go::<true>(&self.board, true);
-                    });
 
-                    for searcher in secondary_searchers.iter_mut() {
-                        s.spawn(|| {
-                            searcher.go::<false>(&self.board, false);
-                        });
-                    }
+                        // the helpers stop with the primary, then their counters are merged in
+                        let mut stats = primary_searcher.stats().clone();
+                        for helper in helpers {
+                            if let Ok(helper_stats) = helper.join() {
+                                stats.merge(&helper_stats);
+                            }
+                        }
+                        stats.print("info string ");
+                    });
 
                     Self::handle_stop_and_quit(&mut self.stored_message);
                 });
 
+                self.time_manager
+                    .spend_nodes(time_args, color, primary_searcher.nodes());
                 primary_searcher.search_complete_actions(&mut self.history);
+
+                if TREE_ENABLED && self.tree_file != Self::TREE_FILE_DEFAULT {
+                    if let Err(e) = primary_searcher.tree().save(self.tree_file.as_str()) {
+                        println!("info string failed to save search tree: {e}");
+                    }
+                }
                 self.tt.age_table();
             }
             UciCommand::SetOptionOverhead(overhead) => {
-                self.time_manager =
-                    TimeManager::new(overhead.clamp(Self::OVERHEAD_MIN, Self::OVERHEAD_MAX));
+                self.time_manager.overhead = overhead.clamp(Self::OVERHEAD_MIN, Self::OVERHEAD_MAX);
             }
             UciCommand::SetOptionHash(megabytes) => {
-                self.tt = TranspositionTable::new(megabytes.clamp(Self::HASH_MIN, Self::HASH_MAX));
+                // drop the old table first so both are never allocated at once
+                self.tt = TranspositionTable::new(0, 1);
+                self.tt = TranspositionTable::new(
+                    megabytes.clamp(Self::HASH_MIN, Self::HASH_MAX),
+                    self.num_threads,
+                );
+                self.print_large_pages();
             }
             UciCommand::SetOptionThreads(count) => {
                 self.num_threads = count.clamp(Self::THREADS_MIN, Self::THREADS_MAX);
@@ -364,16 +750,186 @@ impl UciHandler {
                     self.tablebase.activate(path.as_str());
                 }
             }
+            UciCommand::SetOptionHashFile(path) => {
+                self.hash_file = path;
+            }
+            UciCommand::SetOptionShowWdl(show_wdl) => {
+                self.search_options.show_wdl = show_wdl;
+            }
+            UciCommand::SetOptionNormalizeEval(normalize_eval) => {
+                self.search_options.normalize_eval = normalize_eval;
+            }
+            UciCommand::SetOptionTunable(name, val) => {
+                if TUNING_ENABLED {
+                    tunables::set_tunable(name.as_str(), val);
+                }
+            }
+            UciCommand::SetOptionContempt(contempt) => {
+                self.contempt = contempt.clamp(Self::CONTEMPT_MIN, Self::CONTEMPT_MAX);
+                self.update_contempt();
+            }
+            UciCommand::SetOptionAutoContempt(auto_contempt) => {
+                self.auto_contempt = auto_contempt;
+                self.update_contempt();
+            }
+            UciCommand::SetOptionOpponent(opponent) => {
+                // format: <title> <elo> <computer|human> <name>, where elo may be "none"
+                let elo = opponent
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|elo| elo.parse::<i32>().ok());
+                // an unknown opponent mustn't keep the last one's rating advantage
+                self.rating_adv = elo.map(|elo| Self::ESTIMATED_ELO - elo);
+                self.update_contempt();
+            }
+            UciCommand::SetOptionRatingAdv(rating_adv) => {
+                self.rating_adv =
+                    Some(rating_adv.clamp(Self::RATING_ADV_MIN, Self::RATING_ADV_MAX));
+                self.update_contempt();
+            }
+            UciCommand::SetOptionLimitStrength(limit_strength) => {
+                self.limit_strength = limit_strength;
+                self.update_skill();
+            }
+            UciCommand::SetOptionElo(elo) => {
+                self.elo = elo.clamp(Self::ELO_MIN, Self::ELO_MAX);
+                self.update_skill();
+            }
+            UciCommand::SetOptionSkillLevel(level) => {
+                self.skill_level = level.clamp(Self::SKILL_LEVEL_MIN, Self::SKILL_LEVEL_MAX);
+                self.update_skill();
+            }
+            UciCommand::SetOptionNodesTime(nodes_time) => {
+                self.nodes_time = nodes_time.clamp(Self::NODES_TIME_MIN, Self::NODES_TIME_MAX);
+                self.update_node_clock();
+            }
+            UciCommand::SetOptionDeterministic(deterministic) => {
+                self.search_options.deterministic = deterministic;
+                self.update_node_clock();
+            }
+            UciCommand::SetOptionEvalCache(enabled) => {
+                self.search_options.no_eval_cache = !enabled;
+            }
+            UciCommand::SetOptionQSearchChecks(enabled) => {
+                self.search_options.no_qsearch_checks = !enabled;
+            }
+            UciCommand::SetOptionEvalFile(path) => {
+                // without the feature, a net would be loaded only to never be used
+                if !NNUE_ENABLED {
+                    return;
+                }
+
+                if path == Self::EVAL_FILE_DEFAULT {
+                    nnue::set_loaded_network(None);
+                } else {
+                    match Network::load(&path) {
+                        Ok(net) => {
+                            nnue::set_loaded_network(Some(net));
+                            println!("info string net loaded from {path}");
+                        }
+                        Err(e) => println!("info string failed to load net: {e}"),
+                    }
+                }
+
+                // boards pick up the active net when they are set up
+                self.board = Board::from_fen(&self.board.to_fen());
+            }
+            UciCommand::SetOptionEvalParamsFile(path) => {
+                if path == Self::EVAL_PARAMS_FILE_DEFAULT {
+                    eval_params::set_params(None);
+                } else {
+                    match EvalParams::load(&path) {
+                        Ok(params) => {
+                            eval_params::set_params(Some(params));
+                            println!("info string eval params loaded from {path}");
+                        }
+                        Err(e) => println!("info string failed to load eval params: {e}"),
+                    }
+                }
+            }
+            UciCommand::SetOptionTreeFile(path) => {
+                self.tree_file = path;
+            }
+            UciCommand::SetOptionTreePly(ply) => {
+                self.search_options.tree_ply = ply.clamp(Self::TREE_PLY_MIN, Self::TREE_PLY_MAX);
+            }
+            UciCommand::SetOptionTreeNodes(nodes) => {
+                self.search_options.tree_nodes =
+                    nodes.clamp(Self::TREE_NODES_MIN, Self::TREE_NODES_MAX);
+            }
+            UciCommand::SaveHash => {
+                if self.hash_file == Self::HASH_FILE_DEFAULT {
+                    println!("info string no HashFile set");
+                    return;
+                }
+
+                match self.tt.save(self.hash_file.as_str()) {
+                    Ok(()) => println!("info string hash saved to {}", self.hash_file),
+                    Err(e) => println!("info string failed to save hash: {e}"),
+                }
+            }
+            UciCommand::LoadHash => {
+                if self.hash_file == Self::HASH_FILE_DEFAULT {
+                    println!("info string no HashFile set");
+                    return;
+                }
+
+                match self.tt.load(self.hash_file.as_str()) {
+                    Ok(()) => println!("info string hash loaded from {}", self.hash_file),
+                    Err(e) => println!("info string failed to load hash: {e}"),
+                }
+            }
+            UciCommand::SpsaInput => {
+                for tunable in TUNABLES {
+                    println!("{}", tunable.openbench_string());
+                }
+            }
             UciCommand::StaticEval => {
-                println!(
-                    "Position: {}\nStatic eval: {} cp",
-                    self.board.to_fen(),
-                    evaluate(&self.board)
-                );
+                println!("{}", EvalTable::new(&self.board));
+                // the net's eval when there is one, otherwise the same as the table
+                println!("Static eval: {} cp", evaluate(&self.board));
             }
         }
     }
 
+    // deterministic mode always stops on nodes, so it falls back to a default node clock
+    fn print_large_pages(&self) {
+        let status = if self.tt.large_pages() {
+            "applied"
+        } else {
+            "not applied"
+        };
+        println!("info string large pages {status}");
+    }
+
+    fn update_node_clock(&mut self) {
+        let nodes_time = if self.search_options.deterministic && self.nodes_time == 0 {
+            Self::DETERMINISTIC_NODES_TIME
+        } else {
+            self.nodes_time
+        };
+
+        self.time_manager.set_nodes_time(nodes_time);
+        self.search_options.nodes_time = nodes_time;
+    }
+
+    // UCI_LimitStrength takes priority over Skill Level
+    fn update_skill(&mut self) {
+        self.search_options.skill = if self.limit_strength {
+            Skill::from_elo(self.elo)
+        } else {
+            Skill::from_level(self.skill_level)
+        };
+    }
+
+    fn update_contempt(&mut self) {
+        self.search_options.contempt = match self.rating_adv {
+            Some(rating_adv) if self.auto_contempt => (rating_adv / Self::AUTO_CONTEMPT_ELO_PER_CP)
+                .clamp(-Self::AUTO_CONTEMPT_MAX, Self::AUTO_CONTEMPT_MAX),
+            _ => self.contempt,
+        };
+    }
+
     fn handle_stop_and_quit(stored_message: &mut Option<String>) {
         loop {
             let buffer = Self::read_uci_input();
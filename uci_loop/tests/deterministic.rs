@@ -0,0 +1,73 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+
+const POSITIONS: [&str; 5] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1",
+];
+
+// Runs the engine on every position with a node limit and then with a clock, and returns
+// everything it printed after "uci".
+fn run_engine() -> Vec<String> {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_uci_loop"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start engine");
+
+    let mut stdin = engine.stdin.take().unwrap();
+    let mut stdout = BufReader::new(engine.stdout.take().unwrap()).lines();
+    let mut output = vec![];
+
+    let mut send = |command: &str| writeln!(stdin, "{command}").unwrap();
+    let mut wait_for = |prefix: &str, output: &mut Vec<String>| {
+        for line in stdout.by_ref() {
+            let line = line.unwrap();
+            let done = line.starts_with(prefix);
+            output.push(line);
+            if done {
+                return;
+            }
+        }
+        panic!("engine exited before sending {prefix}");
+    };
+
+    send("uci");
+    wait_for("uciok", &mut vec![]);
+    send("setoption name Threads value 4");
+    send("setoption name Deterministic value true");
+
+    for fen in POSITIONS {
+        send(format!("position fen {fen}").as_str());
+        send("go nodes 20000");
+        wait_for("bestmove", &mut output);
+
+        send(format!("position fen {fen}").as_str());
+        send("go wtime 1000 btime 1000 winc 10 binc 10");
+        wait_for("bestmove", &mut output);
+    }
+
+    send("quit");
+    engine.wait().unwrap();
+    output
+}
+
+#[test]
+fn deterministic_mode_is_repeatable() {
+    let first = run_engine();
+    let second = run_engine();
+
+    assert!(
+        first
+            .iter()
+            .filter(|line| line.starts_with("bestmove"))
+            .count()
+            == 2 * POSITIONS.len()
+    );
+    assert_eq!(first, second);
+}